@@ -0,0 +1,42 @@
+use std::collections::{HashMap, VecDeque};
+use std::hash::Hash;
+use std::sync::{Arc, Mutex};
+
+/// Results of expensive queries, keyed by query. The oldest entry is
+/// dropped once `capacity` is reached.
+pub struct ResultCache<K, V> {
+  capacity: usize,
+  entries: Mutex<Entries<K, V>>,
+}
+
+struct Entries<K, V> {
+  values: HashMap<K, Arc<V>>,
+  order: VecDeque<K>,
+}
+
+impl<K: Hash + Eq + Clone, V> ResultCache<K, V> {
+  pub fn new(capacity: usize) -> Self {
+    ResultCache {
+      capacity,
+      entries: Mutex::new(Entries { values: HashMap::new(), order: VecDeque::new() }),
+    }
+  }
+
+  pub fn get(&self, key: &K) -> Option<Arc<V>> {
+    self.entries.lock().unwrap().values.get(key).cloned()
+  }
+
+  pub fn insert(&self, key: K, value: V) -> Arc<V> {
+    let value = Arc::new(value);
+    let mut entries = self.entries.lock().unwrap();
+    if entries.values.insert(key.clone(), value.clone()).is_none() {
+      entries.order.push_back(key);
+    }
+    while entries.order.len() > self.capacity {
+      if let Some(oldest) = entries.order.pop_front() {
+        entries.values.remove(&oldest);
+      }
+    }
+    value
+  }
+}
@@ -0,0 +1,211 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+use axum::{
+  http::{header, StatusCode},
+  response::{IntoResponse, Response},
+  Json,
+};
+use serde::Serialize;
+
+use crate::graph::CancellationToken;
+
+/// Runs CPU-heavy searches on a dedicated thread pool so they do not hold
+/// up the async runtime.
+///
+/// At most `threads` searches run at once and `max_queued` more wait for a
+/// thread; beyond that new searches are turned away. A search whose request
+/// is dropped, e.g. because the client disconnected, gets its cancellation
+/// token cancelled.
+pub struct SearchExecutor {
+  pool: rayon::ThreadPool,
+  pending: Arc<AtomicUsize>,
+  max_pending: usize,
+}
+
+/// A result along with how long it waited for a thread and how long it ran.
+#[derive(Serialize)]
+pub struct Timed<T> {
+  #[serde(flatten)]
+  pub result: T,
+  pub queue_duration: Duration,
+  pub exec_duration: Duration,
+}
+
+#[derive(Debug)]
+pub enum ExecutorError {
+  /// Too many searches are running or waiting.
+  Busy,
+  /// The search panicked.
+  Failed,
+}
+
+/// A search handed to the pool. Dropping it before it finishes cancels
+/// the search.
+pub struct Running<T> {
+  receiver: tokio::sync::oneshot::Receiver<Timed<T>>,
+  _cancel_on_drop: CancelOnDrop,
+}
+
+/// Decrements the pending count once a search is done with, run or not.
+struct Pending(Arc<AtomicUsize>);
+
+/// Cancels a search when the request waiting for it goes away.
+struct CancelOnDrop(CancellationToken);
+
+impl SearchExecutor {
+  pub const RETRY_AFTER_SECS: u64 = 1;
+
+  pub fn new(threads: usize, max_queued: usize) -> Result<Self, rayon::ThreadPoolBuildError> {
+    let pool = rayon::ThreadPoolBuilder::new()
+      .num_threads(threads)
+      .thread_name(|i| format!("search-{}", i))
+      .build()?;
+    Ok(SearchExecutor { pool, pending: Arc::new(AtomicUsize::new(0)), max_pending: threads + max_queued })
+  }
+
+  /// Number of searches that run at once.
+  pub fn threads(&self) -> usize {
+    self.pool.current_num_threads()
+  }
+
+  pub async fn run<T, F>(&self, job: F) -> Result<Timed<T>, ExecutorError>
+  where
+    T: Send + 'static,
+    F: FnOnce(CancellationToken) -> T + Send + 'static,
+  {
+    self.spawn(job)?.finish().await
+  }
+
+  /// Queues `job` without waiting for it, or turns it away when the pool
+  /// is full.
+  pub fn spawn<T, F>(&self, job: F) -> Result<Running<T>, ExecutorError>
+  where
+    T: Send + 'static,
+    F: FnOnce(CancellationToken) -> T + Send + 'static,
+  {
+    if self.pending.fetch_add(1, Ordering::SeqCst) >= self.max_pending {
+      self.pending.fetch_sub(1, Ordering::SeqCst);
+      return Err(ExecutorError::Busy);
+    }
+    let pending = Pending(self.pending.clone());
+
+    let cancellation = CancellationToken::new();
+    let cancel_on_drop = CancelOnDrop(cancellation.clone());
+    let (sender, receiver) = tokio::sync::oneshot::channel();
+    let queued_at = Instant::now();
+    self.pool.spawn(move || {
+      let queue_duration = queued_at.elapsed();
+      let started_at = Instant::now();
+      // A panic drops the sender, which the waiting request sees as `Failed`.
+      let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| job(cancellation)));
+      // The place is given back before the result, so a request that got
+      // its result can start another search straight away.
+      drop(pending);
+      if let Ok(result) = result {
+        let _ = sender.send(Timed { result, queue_duration, exec_duration: started_at.elapsed() });
+      }
+    });
+
+    Ok(Running { receiver, _cancel_on_drop: cancel_on_drop })
+  }
+}
+
+impl<T> Running<T> {
+  pub async fn finish(self) -> Result<Timed<T>, ExecutorError> {
+    self.receiver.await.map_err(|_| ExecutorError::Failed)
+  }
+}
+
+impl Drop for Pending {
+  fn drop(&mut self) {
+    self.0.fetch_sub(1, Ordering::SeqCst);
+  }
+}
+
+impl Drop for CancelOnDrop {
+  fn drop(&mut self) {
+    self.0.cancel();
+  }
+}
+
+impl<T: Serialize> IntoResponse for Timed<T> {
+  fn into_response(self) -> Response {
+    (StatusCode::OK, Json(self)).into_response()
+  }
+}
+
+impl IntoResponse for ExecutorError {
+  fn into_response(self) -> Response {
+    match self {
+      ExecutorError::Busy => (
+        StatusCode::SERVICE_UNAVAILABLE,
+        [(header::RETRY_AFTER, SearchExecutor::RETRY_AFTER_SECS.to_string())],
+        "too many searches in progress",
+      ).into_response(),
+      ExecutorError::Failed => (StatusCode::INTERNAL_SERVER_ERROR, "search failed").into_response(),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::sync::mpsc;
+
+  /// A job that blocks its thread until `release` is sent to.
+  fn blocking_job() -> (mpsc::Sender<()>, impl FnOnce(CancellationToken) -> u32 + Send + 'static) {
+    let (release, wait) = mpsc::channel();
+    (release, move |_| {
+      wait.recv().unwrap();
+      7
+    })
+  }
+
+  #[tokio::test]
+  async fn full_pool_turns_searches_away() {
+    let executor = SearchExecutor::new(1, 1).unwrap();
+    let (release_running, job) = blocking_job();
+    let running = executor.spawn(job).unwrap();
+    let (release_queued, job) = blocking_job();
+    let queued = executor.spawn(job).unwrap();
+
+    let busy = executor.spawn(|_| 0);
+    assert!(matches!(busy, Err(ExecutorError::Busy)));
+    let response = busy.err().unwrap().into_response();
+    assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(response.headers()[header::RETRY_AFTER], SearchExecutor::RETRY_AFTER_SECS.to_string());
+
+    release_running.send(()).unwrap();
+    release_queued.send(()).unwrap();
+    assert_eq!(running.finish().await.unwrap().result, 7);
+    let queued = queued.finish().await.unwrap();
+    assert!(queued.queue_duration > Duration::ZERO);
+
+    // Finished searches give their places back.
+    assert_eq!(executor.run(|_| 1).await.unwrap().result, 1);
+  }
+
+  #[tokio::test]
+  async fn dropped_search_is_cancelled() {
+    let executor = SearchExecutor::new(1, 0).unwrap();
+    let (cancelled, wait) = mpsc::channel();
+    let running = executor.spawn(move |cancellation: CancellationToken| {
+      while !cancellation.is_cancelled() {
+        std::thread::sleep(Duration::from_millis(1));
+      }
+      cancelled.send(()).unwrap();
+    }).unwrap();
+    drop(running);
+    wait.recv_timeout(Duration::from_secs(10)).unwrap();
+  }
+
+  #[tokio::test]
+  async fn panicking_search_fails() {
+    let executor = SearchExecutor::new(1, 0).unwrap();
+    let result = executor.run(|_| -> u32 { panic!("search bug") }).await;
+    assert!(matches!(result, Err(ExecutorError::Failed)));
+    assert_eq!(executor.run(|_| 2).await.unwrap().result, 2);
+  }
+}
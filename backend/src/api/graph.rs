@@ -1,23 +1,425 @@
 use std::sync::Arc;
+use std::time::Duration;
 
 use axum::{
   extract::{Query, State},
   http::StatusCode,
-  response::IntoResponse,
+  response::{sse::{Event, KeepAlive, Sse}, IntoResponse, Response},
   Json,
 };
+use futures_util::stream;
+
+use crate::api::Timed;
 
 pub async fn graph_search(
   State(state): State<Arc<crate::api::State>>,
   Query(params): Query<crate::graph::GraphSearchQuery>,
-) -> impl IntoResponse {
-  let mut graph_search = crate::graph::GraphSearch::new(
-    state.graph.clone(),
-    state.title_to_index.clone(),
-    params
-  );
+) -> Response {
+  let job_state = state.clone();
+  let result = state.executor.run(move |cancellation| {
+    let state = job_state;
+    let mut graph_search = crate::graph::GraphSearch::with_workspace(
+      state.graph.clone(),
+      state.title_to_index.clone(),
+      state.id_to_index.clone(),
+      params,
+      state.workspaces.take(),
+    )
+    .with_oracle(state.distance_oracle.clone())
+    .with_cancellation(cancellation);
+
+    let result = graph_search.exec();
+    state.workspaces.put(graph_search.into_workspace());
+    result
+  }).await;
+
+  result.into_response()
+}
+
+/// Like `graph_search`, but streams `progress` events while searching and
+/// ends with a `result` event. The search is cancelled if the client goes
+/// away.
+pub async fn graph_search_stream(
+  State(state): State<Arc<crate::api::State>>,
+  Query(params): Query<crate::graph::GraphSearchQuery>,
+) -> Response {
+  let (sender, receiver) = tokio::sync::mpsc::unbounded_channel();
+  let job_state = state.clone();
+  let running = state.executor.spawn(move |cancellation| {
+    let state = job_state;
+    let mut graph_search = crate::graph::GraphSearch::with_workspace(
+      state.graph.clone(),
+      state.title_to_index.clone(),
+      state.id_to_index.clone(),
+      params,
+      state.workspaces.take(),
+    )
+    .with_oracle(state.distance_oracle.clone())
+    .with_cancellation(cancellation)
+    .with_progress(move |progress| {
+      let _ = sender.send(Event::default().event("progress").json_data(progress));
+    });
+
+    let result = graph_search.exec();
+    state.workspaces.put(graph_search.into_workspace());
+    result
+  });
+  let running = match running {
+    Ok(running) => running,
+    Err(error) => return error.into_response(),
+  };
+
+  // Progress events end when the search drops its sender; the result follows.
+  let events = stream::unfold(Some((receiver, running)), |state| async move {
+    let (mut receiver, running) = state?;
+    match receiver.recv().await {
+      Some(event) => Some((event, Some((receiver, running)))),
+      None => {
+        let event = match running.finish().await {
+          Ok(result) => Event::default().event("result").json_data(result),
+          Err(_) => Ok(Event::default().event("error").data("search failed")),
+        };
+        Some((event, None))
+      },
+    }
+  });
+
+  Sse::new(events).keep_alive(KeepAlive::default()).into_response()
+}
+
+pub async fn graph_search_paths(
+  State(state): State<Arc<crate::api::State>>,
+  Query(params): Query<crate::graph::GraphSearchQuery>,
+  Query(page): Query<crate::graph::ShortestPathsQuery>,
+) -> Response {
+  let job_state = state.clone();
+  let result = state.executor.run(move |cancellation| {
+    let state = job_state;
+    let mut graph_search = crate::graph::GraphSearch::with_workspace(
+      state.graph.clone(),
+      state.title_to_index.clone(),
+      state.id_to_index.clone(),
+      params,
+      state.workspaces.take(),
+    )
+    .with_oracle(state.distance_oracle.clone())
+    .with_cancellation(cancellation);
+
+    let result = graph_search.exec();
+    state.workspaces.put(graph_search.into_workspace());
+    crate::graph::ShortestPathsResult::new(&result, &page)
+  }).await;
+
+  match result {
+    Ok(Timed { result: Ok(paths), queue_duration, exec_duration }) => {
+      Timed { result: paths, queue_duration, exec_duration }.into_response()
+    },
+    Ok(Timed { result: Err(message), .. }) => (StatusCode::BAD_REQUEST, message).into_response(),
+    Err(error) => error.into_response(),
+  }
+}
+
+/// Runs the queries in the body, a JSON array or one JSON query per line.
+pub async fn graph_search_batch(
+  State(state): State<Arc<crate::api::State>>,
+  Query(batch_query): Query<crate::graph::BatchSearchQuery>,
+  body: String,
+) -> Response {
+  let queries = match parse_batch(&body) {
+    Ok(queries) => queries,
+    Err(message) => return (StatusCode::BAD_REQUEST, message).into_response(),
+  };
+  if queries.len() > crate::graph::BatchSearch::MAX_QUERIES {
+    let message = format!("at most {} queries per batch", crate::graph::BatchSearch::MAX_QUERIES);
+    return (StatusCode::PAYLOAD_TOO_LARGE, message).into_response();
+  }
+
+  // The queries are split over one job per thread, so a batch counts
+  // against the executor's limit for every thread it keeps busy.
+  let start_time = std::time::Instant::now();
+  let chunk_size = queries.len().div_ceil(state.executor.threads()).max(1);
+  let mut queries = queries.into_iter().peekable();
+  let mut running = Vec::new();
+  while queries.peek().is_some() {
+    let chunk: Vec<crate::graph::GraphSearchQuery> = queries.by_ref().take(chunk_size).collect();
+    let job_state = state.clone();
+    let batch_query = batch_query.clone();
+    let job = move |cancellation| {
+      let state = job_state;
+      let batch_search = crate::graph::BatchSearch::new(state.graph.clone(), state.title_to_index.clone(), state.id_to_index.clone())
+        .with_oracle(state.distance_oracle.clone())
+        .with_cancellation(cancellation);
+      chunk
+        .into_iter()
+        .map(|query| batch_search.search(query, &batch_query, &state.workspaces))
+        .collect::<Vec<_>>()
+    };
+    // Dropping the chunks already queued cancels them.
+    match state.executor.spawn(job) {
+      Ok(chunk) => running.push(chunk),
+      Err(error) => return error.into_response(),
+    }
+  }
+
+  let mut items = Vec::new();
+  let (mut queue_duration, mut exec_duration) = (Duration::ZERO, Duration::ZERO);
+  for chunk in running {
+    match chunk.finish().await {
+      Ok(timed) => {
+        items.extend(timed.result);
+        queue_duration = queue_duration.max(timed.queue_duration);
+        exec_duration = exec_duration.max(timed.exec_duration);
+      },
+      Err(error) => return error.into_response(),
+    }
+  }
+  let result = crate::graph::BatchSearchResult::new(items, start_time.elapsed());
+  Timed { result, queue_duration, exec_duration }.into_response()
+}
+
+fn parse_batch(body: &str) -> Result<Vec<crate::graph::GraphSearchQuery>, String> {
+  if body.trim_start().starts_with('[') {
+    return serde_json::from_str(body).map_err(|e| format!("invalid query array: {}", e));
+  }
+  body
+    .lines()
+    .enumerate()
+    .filter(|(_, line)| !line.trim().is_empty())
+    .map(|(i, line)| serde_json::from_str(line).map_err(|e| format!("invalid query on line {}: {}", i + 1, e)))
+    .collect()
+}
+
+pub async fn k_paths(
+  State(state): State<Arc<crate::api::State>>,
+  Query(params): Query<crate::graph::GraphSearchQuery>,
+  Query(k_query): Query<crate::graph::KShortestPathsQuery>,
+) -> Response {
+  if let Some(parameter) = params.unsupported_parameter() {
+    return (StatusCode::BAD_REQUEST, format!("{} is not supported for k paths", parameter)).into_response();
+  }
+
+  let job_state = state.clone();
+  let result = state.executor.run(move |cancellation| {
+    let state = job_state;
+    let mut k_shortest_paths = crate::graph::KShortestPaths::with_workspace(
+      state.graph.clone(),
+      state.title_to_index.clone(),
+      state.id_to_index.clone(),
+      params,
+      state.workspaces.take(),
+    )
+    .with_cancellation(cancellation);
+
+    let result = k_shortest_paths.exec(&k_query);
+    state.workspaces.put(k_shortest_paths.into_workspace());
+    result
+  }).await;
+
+  result.into_response()
+}
+
+pub async fn weighted_search(
+  State(state): State<Arc<crate::api::State>>,
+  Query(params): Query<crate::graph::GraphSearchQuery>,
+  Query(costs): Query<crate::graph::EdgeCostQuery>,
+) -> Response {
+  if let Some(parameter) = params.unsupported_parameter() {
+    return (StatusCode::BAD_REQUEST, format!("{} is not supported for weighted search", parameter)).into_response();
+  }
+
+  let job_state = state.clone();
+  let result = state.executor.run(move |cancellation| {
+    let state = job_state;
+    let mut weighted_search = crate::graph::WeightedSearch::with_workspace(
+      state.graph.clone(),
+      state.edge_attributes.clone(),
+      state.title_to_index.clone(),
+      state.id_to_index.clone(),
+      params,
+      costs,
+      state.workspaces.take(),
+    )
+    .with_oracle(state.distance_oracle.clone())
+    .with_cancellation(cancellation);
+
+    let result = weighted_search.exec();
+    state.workspaces.put(weighted_search.into_workspace());
+    result
+  }).await;
+
+  result.into_response()
+}
+
+pub async fn distance_bounds(
+  State(state): State<Arc<crate::api::State>>,
+  Query(params): Query<crate::graph::DistanceBoundsQuery>,
+) -> Response {
+  let Some(distance_oracle) = &state.distance_oracle else {
+    return (StatusCode::NOT_FOUND, "no landmarks loaded").into_response();
+  };
+
+  let result = distance_oracle.query(state.graph.as_ref(), &state.title_to_index, &state.id_to_index, &params);
+
+  (StatusCode::OK, Json(result)).into_response()
+}
+
+pub async fn neighborhood(
+  State(state): State<Arc<crate::api::State>>,
+  Query(params): Query<crate::graph::NeighborhoodQuery>,
+) -> Response {
+  let job_state = state.clone();
+  let result = state.executor.run(move |cancellation| {
+    let state = job_state;
+    crate::graph::Neighborhood::new(state.graph.clone(), state.title_to_index.clone(), state.id_to_index.clone(), params)
+      .with_cancellation(cancellation)
+      .exec()
+  }).await;
+
+  result.into_response()
+}
+
+pub async fn reachability(
+  State(state): State<Arc<crate::api::State>>,
+  Query(params): Query<crate::graph::ReachabilityQuery>,
+) -> Response {
+  // Each query walks the whole graph, so results are kept.
+  if let Some(result) = state.reachability.get(&params) {
+    return Timed { result: result.as_ref(), queue_duration: Duration::ZERO, exec_duration: Duration::ZERO }.into_response();
+  }
+
+  let job_state = state.clone();
+  let result = state.executor.run(move |cancellation| {
+    let state = job_state;
+    let mut reachability = crate::graph::Reachability::with_workspace(
+      state.graph.clone(),
+      state.title_to_index.clone(),
+      state.id_to_index.clone(),
+      params.clone(),
+      state.workspaces.take(),
+    )
+    .with_cancellation(cancellation.clone());
+    let result = reachability.exec();
+    state.workspaces.put(reachability.into_workspace());
+    // A walk cut short is not kept; nobody is waiting for it anyway.
+    if cancellation.is_cancelled() {
+      return Arc::new(result);
+    }
+    state.reachability.insert(params, result)
+  }).await;
+
+  match result {
+    Ok(Timed { result, queue_duration, exec_duration }) => {
+      Timed { result: result.as_ref(), queue_duration, exec_duration }.into_response()
+    },
+    Err(error) => error.into_response(),
+  }
+}
+
+pub async fn random_pairs(
+  State(state): State<Arc<crate::api::State>>,
+  Query(params): Query<crate::graph::RandomPairsQuery>,
+) -> Response {
+  let job_state = state.clone();
+  let result = state.executor.run(move |cancellation| {
+    let state = job_state;
+    let mut random_pairs = crate::graph::RandomPairs::with_workspace(
+      state.graph.clone(),
+      state.title_to_index.clone(),
+      state.id_to_index.clone(),
+      params,
+      state.workspaces.take(),
+    )
+    .with_cancellation(cancellation);
+    let result = random_pairs.exec();
+    state.workspaces.put(random_pairs.into_workspace());
+    result
+  }).await;
+
+  result.into_response()
+}
+
+pub async fn click_path(
+  State(state): State<Arc<crate::api::State>>,
+  Query(params): Query<crate::graph::ClickPathQuery>,
+) -> Response {
+  let job_state = state.clone();
+  let result = state.executor.run(move |cancellation| {
+    let state = job_state;
+    let mut click_path = crate::graph::ClickPath::with_workspace(
+      state.graph.clone(),
+      state.title_to_index.clone(),
+      state.id_to_index.clone(),
+      params,
+      state.workspaces.take(),
+    )
+    .with_cancellation(cancellation);
+    let result = click_path.exec();
+    state.workspaces.put(click_path.into_workspace());
+    result
+  }).await;
+
+  result.into_response()
+}
+
+pub async fn stats(
+  State(state): State<Arc<crate::api::State>>,
+) -> Response {
+  if let Some(stats) = state.stats.get() {
+    return Timed { result: stats.as_ref(), queue_duration: Duration::ZERO, exec_duration: Duration::ZERO }.into_response();
+  }
+
+  let job_state = state.clone();
+  let result = state.executor.run(move |cancellation| {
+    let state = job_state;
+    let top = crate::graph::GraphStats::DEFAULT_TOP;
+    let stats = crate::graph::GraphStats::compute_with_cancellation(state.graph.as_ref(), top, &cancellation)?;
+    Some(state.stats.get_or_init(|| Arc::new(stats)).clone())
+  }).await;
+
+  match result {
+    Ok(Timed { result: Some(result), queue_duration, exec_duration }) => {
+      Timed { result: result.as_ref(), queue_duration, exec_duration }.into_response()
+    },
+    Ok(Timed { result: None, .. }) => (StatusCode::SERVICE_UNAVAILABLE, "stats computation cancelled").into_response(),
+    Err(error) => error.into_response(),
+  }
+}
+
+pub async fn graph_search_diff(
+  State(state): State<Arc<crate::api::State>>,
+  Query(params): Query<crate::graph::GraphSearchQuery>,
+) -> Response {
+  if state.previous.is_none() {
+    return (StatusCode::NOT_FOUND, "no previous snapshot loaded").into_response();
+  }
+
+  let job_state = state.clone();
+  let result = state.executor.run(move |cancellation| {
+    let state = job_state;
+    let previous = state.previous.as_ref().unwrap();
+    let previous_result = crate::graph::GraphSearch::new(
+      previous.graph.clone(),
+      previous.title_to_index.clone(),
+      previous.id_to_index.clone(),
+      params.clone()
+    )
+    .with_cancellation(cancellation.clone())
+    .exec();
+
+    let mut graph_search = crate::graph::GraphSearch::with_workspace(
+      state.graph.clone(),
+      state.title_to_index.clone(),
+      state.id_to_index.clone(),
+      params,
+      state.workspaces.take(),
+    )
+    .with_oracle(state.distance_oracle.clone())
+    .with_cancellation(cancellation);
+    let current_result = graph_search.exec();
+    state.workspaces.put(graph_search.into_workspace());
 
-  let result = graph_search.exec();
+    crate::graph_diff::PathDiff::new(previous_result, current_result)
+  }).await;
 
-  return (StatusCode::OK, Json(result));
-}
\ No newline at end of file
+  result.into_response()
+}
@@ -1,7 +1,27 @@
 mod state;
+mod cache;
+mod executor;
 mod search;
 mod graph;
 
 pub use state::State;
+pub use state::Snapshot;
+pub use cache::ResultCache;
+pub use executor::SearchExecutor;
+pub use executor::ExecutorError;
+pub use executor::Running;
+pub use executor::Timed;
 pub use search::search;
-pub use graph::graph_search;
\ No newline at end of file
+pub use graph::graph_search;
+pub use graph::graph_search_stream;
+pub use graph::graph_search_paths;
+pub use graph::graph_search_batch;
+pub use graph::graph_search_diff;
+pub use graph::k_paths;
+pub use graph::weighted_search;
+pub use graph::distance_bounds;
+pub use graph::neighborhood;
+pub use graph::reachability;
+pub use graph::random_pairs;
+pub use graph::click_path;
+pub use graph::stats;
\ No newline at end of file
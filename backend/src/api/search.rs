@@ -2,15 +2,15 @@ use std::sync::Arc;
 
 use axum::{
   extract::{Query, State},
-  http::StatusCode,
-  response::IntoResponse,
-  Json,
+  response::{IntoResponse, Response},
 };
 
 pub async fn search(
   State(state): State<Arc<crate::api::State>>,
   Query(params): Query<crate::title_search::TitleSearchQuery>,
-) -> impl IntoResponse {
-  let result = state.title_search.search(&params);
-  return (StatusCode::OK, Json(result));
+) -> Response {
+  let job_state = state.clone();
+  let result = state.executor.run(move |_| job_state.title_search.search(&params)).await;
+
+  result.into_response()
 }
\ No newline at end of file
@@ -1,20 +1,87 @@
 use std::sync::Arc;
+use crate::graph::GraphView;
+
 pub struct State {
   pub graph: Arc<crate::graph::Graph>,
   pub title_search: Arc<crate::title_search::TitleSearch>,
   pub title_to_index: Arc<std::collections::HashMap<String, usize>>,
+  /// Resolves the `#<page id>` entries of queries.
+  pub id_to_index: Arc<std::collections::HashMap<u32, usize>>,
+  /// Link attributes for `weighted_search`, when they were extracted.
+  pub edge_attributes: Option<Arc<crate::graph::EdgeAttributes>>,
+  /// Landmark distances for pruning searches and estimating distances.
+  pub distance_oracle: Option<Arc<crate::graph::DistanceOracle>>,
+  pub workspaces: crate::graph::SearchWorkspacePool,
+  pub executor: crate::api::SearchExecutor,
+  /// Whole-graph statistics, read from the file next to the graph or
+  /// computed on first use.
+  pub stats: std::sync::OnceLock<Arc<crate::graph::GraphStats>>,
+  pub reachability: crate::api::ResultCache<crate::graph::ReachabilityQuery, crate::graph::ReachabilityResult>,
+  pub previous: Option<Snapshot>,
+}
+
+/// An older graph kept around to compare search results against.
+pub struct Snapshot {
+  pub graph: Arc<crate::graph::Graph>,
+  pub title_to_index: Arc<std::collections::HashMap<String, usize>>,
+  pub id_to_index: Arc<std::collections::HashMap<u32, usize>>,
 }
 
 impl State {
-  pub fn new(graph: Arc<crate::graph::Graph>) -> Self {
+  /// Fails when the search threads cannot be started.
+  pub fn new(graph: Arc<crate::graph::Graph>) -> Result<Self, rayon::ThreadPoolBuildError> {
     let title_search = Arc::new(crate::title_search::TitleSearch::new(graph.clone()));
-    let title_to_index = Arc::new(graph
-      .nodes
-      .iter()
-      .enumerate()
-      .map(|(i, page)| (page.title.clone(), i))
-      .collect());
-
-    State { graph, title_search, title_to_index }
+    let title_to_index = Arc::new(title_to_index(graph.as_ref()));
+    let id_to_index = Arc::new(id_to_index(graph.as_ref()));
+    let max_idle = std::thread::available_parallelism().map(|n| n.get()).unwrap_or(1);
+    let workspaces = crate::graph::SearchWorkspacePool::new(graph.node_count(), max_idle);
+    let executor = crate::api::SearchExecutor::new(max_idle, max_idle * 4)?;
+
+    let reachability = crate::api::ResultCache::new(64);
+
+    Ok(State { graph, title_search, title_to_index, id_to_index, edge_attributes: None, distance_oracle: None, workspaces, executor, stats: std::sync::OnceLock::new(), reachability, previous: None })
+  }
+
+  /// Ignores attributes that were written for a different graph.
+  pub fn with_edge_attributes(mut self, edge_attributes: Arc<crate::graph::EdgeAttributes>) -> Self {
+    if edge_attributes.matches(self.graph.as_ref()) {
+      self.edge_attributes = Some(edge_attributes);
+    }
+    self
+  }
+
+  /// Ignores an oracle that was built for a different graph.
+  pub fn with_distance_oracle(mut self, distance_oracle: Arc<crate::graph::DistanceOracle>) -> Self {
+    if distance_oracle.matches(self.graph.as_ref()) {
+      self.distance_oracle = Some(distance_oracle);
+    }
+    self
+  }
+
+  /// Ignores stats that were computed for a different graph.
+  pub fn with_stats(self, stats: Arc<crate::graph::GraphStats>) -> Self {
+    if stats.matches(self.graph.as_ref()) {
+      let _ = self.stats.set(stats);
+    }
+    self
+  }
+
+  pub fn with_previous(mut self, graph: Arc<crate::graph::Graph>) -> Self {
+    let title_to_index = Arc::new(title_to_index(graph.as_ref()));
+    let id_to_index = Arc::new(id_to_index(graph.as_ref()));
+    self.previous = Some(Snapshot { graph, title_to_index, id_to_index });
+    self
   }
-}
\ No newline at end of file
+}
+
+fn title_to_index<G: GraphView>(graph: &G) -> std::collections::HashMap<String, usize> {
+  (0..graph.node_count())
+    .map(|i| (graph.title(i).to_string(), i))
+    .collect()
+}
+
+fn id_to_index<G: GraphView>(graph: &G) -> std::collections::HashMap<u32, usize> {
+  (0..graph.node_count())
+    .map(|i| (graph.id(i), i))
+    .collect()
+}
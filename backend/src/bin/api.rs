@@ -1,4 +1,4 @@
-use axum::{routing::get, Router};
+use axum::{routing::{get, post}, Router};
 use std::sync::Arc;
 use std::fs::File;
 use std::io::Read;
@@ -10,15 +10,74 @@ fn read_graph(path: &str) -> graphipedia::graph::Graph {
   bincode::deserialize(&buf).unwrap()
 }
 
+/// `None` for files in an older format, which are then left unused.
+fn read_edge_attributes(path: &str) -> Option<graphipedia::graph::EdgeAttributes> {
+  let mut file = File::open(path).unwrap();
+  let mut buf = Vec::new();
+  file.read_to_end(&mut buf).unwrap();
+  bincode::deserialize(&buf).ok()
+}
+
+/// `None` for files in an older format, which are then left unused.
+fn read_stats(path: &str) -> Option<graphipedia::graph::GraphStats> {
+  let file = File::open(path).unwrap();
+  serde_json::from_reader(std::io::BufReader::new(file)).ok()
+}
+
+/// `None` for files in an older format, which are then left unused.
+fn read_distance_oracle(path: &str) -> Option<graphipedia::graph::DistanceOracle> {
+  let mut file = File::open(path).unwrap();
+  let mut buf = Vec::new();
+  file.read_to_end(&mut buf).unwrap();
+  bincode::deserialize(&buf).ok()
+}
+
 #[tokio::main]
 async fn main() {
   let graph = Arc::new(read_graph("graph.bin"));
-  let state = Arc::new(graphipedia::api::State::new(graph.clone()));
+  let mut state = graphipedia::api::State::new(graph.clone()).expect("failed to start search threads");
+  let edge_attributes_path = graphipedia::graph::EdgeAttributes::path_for("graph.bin");
+  if std::path::Path::new(&edge_attributes_path).exists() {
+    match read_edge_attributes(&edge_attributes_path) {
+      Some(edge_attributes) => state = state.with_edge_attributes(Arc::new(edge_attributes)),
+      None => eprintln!("Ignoring unreadable edge attributes: {}", edge_attributes_path),
+    }
+  }
+  let distance_oracle_path = graphipedia::graph::DistanceOracle::path_for("graph.bin");
+  if std::path::Path::new(&distance_oracle_path).exists() {
+    match read_distance_oracle(&distance_oracle_path) {
+      Some(distance_oracle) => state = state.with_distance_oracle(Arc::new(distance_oracle)),
+      None => eprintln!("Ignoring unreadable landmarks: {}", distance_oracle_path),
+    }
+  }
+  let stats_path = graphipedia::graph::GraphStats::path_for("graph.bin");
+  if std::path::Path::new(&stats_path).exists() {
+    match read_stats(&stats_path) {
+      Some(stats) => state = state.with_stats(Arc::new(stats)),
+      None => eprintln!("Ignoring unreadable stats: {}", stats_path),
+    }
+  }
+  if std::path::Path::new("previous_graph.bin").exists() {
+    state = state.with_previous(Arc::new(read_graph("previous_graph.bin")));
+  }
+  let state = Arc::new(state);
 
   let app = Router::new()
     .route("/", get(|| async { "graphipedia" }))
     .route("/api/search", get(graphipedia::api::search)).with_state(state.clone())
-    .route("/api/graph_search", get(graphipedia::api::graph_search)).with_state(state.clone());
+    .route("/api/graph_search", get(graphipedia::api::graph_search)).with_state(state.clone())
+    .route("/api/graph_search/stream", get(graphipedia::api::graph_search_stream)).with_state(state.clone())
+    .route("/api/graph_search/paths", get(graphipedia::api::graph_search_paths)).with_state(state.clone())
+    .route("/api/graph_search/batch", post(graphipedia::api::graph_search_batch)).with_state(state.clone())
+    .route("/api/graph_search/diff", get(graphipedia::api::graph_search_diff)).with_state(state.clone())
+    .route("/api/k_paths", get(graphipedia::api::k_paths)).with_state(state.clone())
+    .route("/api/weighted_search", get(graphipedia::api::weighted_search)).with_state(state.clone())
+    .route("/api/distance_bounds", get(graphipedia::api::distance_bounds)).with_state(state.clone())
+    .route("/api/neighborhood", get(graphipedia::api::neighborhood)).with_state(state.clone())
+    .route("/api/reachability", get(graphipedia::api::reachability)).with_state(state.clone())
+    .route("/api/random_pairs", get(graphipedia::api::random_pairs)).with_state(state.clone())
+    .route("/api/click_path", get(graphipedia::api::click_path)).with_state(state.clone())
+    .route("/api/stats", get(graphipedia::api::stats)).with_state(state.clone());
 
   let listener = tokio::net::TcpListener::bind("0.0.0.0:3000").await.unwrap();
 
@@ -1,20 +1,108 @@
+use std::collections::HashMap;
 use std::fs::File;
-use std::io::Read;
+use std::io::{BufRead, BufReader, Read};
 use std::sync::Arc;
+use graphipedia::graph::GraphView;
+
+fn usage() -> ! {
+  eprintln!("usage: cli [--batch <pairs.tsv> [--sample-path]]");
+  std::process::exit(1);
+}
+
+/// Searches every `start<TAB>end` line of `pairs_path` and prints one
+/// `start<TAB>end<TAB>distance<TAB>path count[<TAB>path]` line per pair.
+/// A line that is not a pair is printed back with an `error:` column
+/// instead, and the other pairs are still searched.
+fn run_batch(
+  graph: Arc<graphipedia::graph::Graph>,
+  title_to_index_map: Arc<HashMap<String, usize>>,
+  id_to_index_map: Arc<HashMap<u32, usize>>,
+  pairs_path: &str,
+  sample_path: bool,
+) {
+  let file = File::open(pairs_path).unwrap();
+  let pairs: Vec<Result<graphipedia::graph::GraphSearchQuery, String>> = BufReader::new(file)
+    .lines()
+    .map(|line| line.unwrap())
+    .filter(|line| !line.trim().is_empty())
+    .map(|line| {
+      // `trim` also drops the `\r` of CRLF line ends.
+      let pair = line.split_once('\t').map(|(start, end)| (start.trim(), end.trim()));
+      match pair {
+        Some((start, end)) if !start.is_empty() && !end.is_empty() => Ok(graphipedia::graph::GraphSearchQuery {
+          start: start.to_string(),
+          end: end.to_string(),
+          enable_date_related: Some(false),
+          enable_list_article: Some(false),
+          ..Default::default()
+        }),
+        _ => Err(line.trim().to_string()),
+      }
+    })
+    .collect();
+  let queries = pairs.iter().filter_map(|pair| pair.as_ref().ok()).cloned().collect();
+
+  let workspaces = graphipedia::graph::SearchWorkspacePool::new(graph.node_count(), rayon::current_num_threads());
+  let batch_query = graphipedia::graph::BatchSearchQuery { sample_path: Some(sample_path) };
+  let result = graphipedia::graph::BatchSearch::new(graph, title_to_index_map, id_to_index_map)
+    .exec(queries, &batch_query, &workspaces);
+
+  let mut items = result.items.iter();
+  let mut invalid = 0;
+  for pair in &pairs {
+    if let Err(line) = pair {
+      invalid += 1;
+      println!("{}\terror: expected start<TAB>end", line);
+      continue;
+    }
+    let item = items.next().unwrap();
+    let distance = item.distance.map_or("-".to_string(), |distance| distance.to_string());
+    let mut line = format!("{}\t{}\t{}\t{}", item.start, item.end, distance, item.path_count);
+    if let Some(path) = &item.path {
+      let titles: Vec<&str> = path.iter().map(|node| node.title.as_str()).collect();
+      line.push('\t');
+      line.push_str(&titles.join(" > "));
+    }
+    println!("{}", line);
+  }
+  eprintln!("{} of {} routes found in {:?}", result.route_found, result.items.len(), result.duration);
+  if invalid > 0 {
+    eprintln!("{} invalid lines skipped", invalid);
+  }
+}
 
 fn main() {
+  let args: Vec<String> = std::env::args().collect();
+  let mut batch_path = None;
+  let mut sample_path = false;
+  let mut rest = args[1..].iter();
+  while let Some(arg) = rest.next() {
+    match arg.as_str() {
+      "--batch" => batch_path = Some(rest.next().unwrap_or_else(|| usage()).clone()),
+      "--sample-path" => sample_path = true,
+      _ => usage(),
+    }
+  }
+
   let path = "graph.bin";
   let mut file = File::open(path).unwrap();
   let mut buf = Vec::new();
   file.read_to_end(&mut buf).unwrap();
   let graph: graphipedia::graph::Graph = bincode::deserialize(&buf).unwrap();
   let graph = Arc::new(graph);
-  let title_to_index_map: std::collections::HashMap<String, usize> = graph.nodes
-    .iter()
-    .enumerate()
-    .map(|(i, page)| (page.title.clone(), i))
+  let title_to_index_map: HashMap<String, usize> = (0..graph.node_count())
+    .map(|i| (graph.title(i).to_string(), i))
     .collect();
   let title_to_index_map = Arc::new(title_to_index_map);
+  let id_to_index_map: HashMap<u32, usize> = (0..graph.node_count())
+    .map(|i| (graph.id(i), i))
+    .collect();
+  let id_to_index_map = Arc::new(id_to_index_map);
+
+  if let Some(batch_path) = batch_path {
+    run_batch(graph, title_to_index_map, id_to_index_map, &batch_path, sample_path);
+    return;
+  }
 
   loop {
     let mut start = String::new();
@@ -41,11 +129,13 @@ fn main() {
         end: end.to_string(),
         enable_date_related: Some(false),
         enable_list_article: Some(false),
+        ..Default::default()
     };
 
     let mut graph_search = graphipedia::graph::GraphSearch::new(
         graph.clone(),
         title_to_index_map.clone(),
+        id_to_index_map.clone(),
         query,
     );
 
@@ -0,0 +1,53 @@
+use std::env;
+use std::fs::File;
+use std::io::{Read, Write};
+
+fn read_graph(path: &str) -> graphipedia::graph::Graph {
+  let mut file = File::open(path).unwrap();
+  let mut buf = Vec::new();
+  file.read_to_end(&mut buf).unwrap();
+  bincode::deserialize(&buf).unwrap()
+}
+
+fn usage() -> ! {
+  eprintln!("usage: diff <old.bin> <new.bin> [--json <report.json>] [--limit <n>] [--top <n>]");
+  std::process::exit(1);
+}
+
+fn main() {
+  let args: Vec<String> = env::args().collect();
+  if args.len() < 3 {
+    usage();
+  }
+  let old_path = &args[1];
+  let new_path = &args[2];
+
+  let mut json_path = None;
+  let mut sample_limit = 100;
+  let mut top_limit = 20;
+  let mut rest = args[3..].iter();
+  while let Some(arg) = rest.next() {
+    match arg.as_str() {
+      "--json" => json_path = Some(rest.next().unwrap_or_else(|| usage())),
+      "--limit" => sample_limit = rest.next().and_then(|n| n.parse().ok()).unwrap_or_else(|| usage()),
+      "--top" => top_limit = rest.next().and_then(|n| n.parse().ok()).unwrap_or_else(|| usage()),
+      _ => usage(),
+    }
+  }
+
+  println!("Reading graph from: {}", old_path);
+  let old_graph = read_graph(old_path);
+  println!("Reading graph from: {}", new_path);
+  let new_graph = read_graph(new_path);
+
+  let report = graphipedia::graph_diff::GraphDiff::new(sample_limit, top_limit).diff(&old_graph, &new_graph);
+  println!();
+  print!("{}", report);
+
+  if let Some(json_path) = json_path {
+    let mut file = File::create(json_path).unwrap();
+    serde_json::to_writer_pretty(&mut file, &report).unwrap();
+    file.write_all(b"\n").unwrap();
+    println!("\nWrote JSON report to: {}", json_path);
+  }
+}
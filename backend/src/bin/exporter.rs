@@ -0,0 +1,93 @@
+use std::fs::File;
+use std::io::Read;
+use std::sync::Arc;
+use std::env;
+use graphipedia::graph::GraphView;
+use graphipedia::graph_exporter::{Exporter, Format};
+
+fn read_graph(path: &str) -> graphipedia::graph::Graph {
+  let mut file = File::open(path).unwrap();
+  let mut buf = Vec::new();
+  file.read_to_end(&mut buf).unwrap();
+  bincode::deserialize(&buf).unwrap()
+}
+
+struct Filter {
+  exclude_redirects: bool,
+  exclude_date_related: bool,
+  exclude_list_article: bool,
+  ns: Option<i32>,
+}
+
+impl Filter {
+  fn is_empty(&self) -> bool {
+    !self.exclude_redirects && !self.exclude_date_related && !self.exclude_list_article && self.ns.is_none()
+  }
+
+  fn keep(&self, graph: &graphipedia::graph::Graph, index: usize) -> bool {
+    if self.exclude_redirects && graph.is_redirect(index) {
+      return false;
+    }
+    if self.exclude_date_related && graph.is_date_related(index) {
+      return false;
+    }
+    if self.exclude_list_article && graph.is_list_article(index) {
+      return false;
+    }
+    if let Some(ns) = self.ns {
+      if graph.ns(index) != ns {
+        return false;
+      }
+    }
+    true
+  }
+}
+
+fn usage() -> ! {
+  eprintln!("usage: exporter <tsv|nodes-tsv|graphml|gexf|dot|csv> <output> [graph.bin] [--exclude-redirects] [--exclude-date-related] [--exclude-list-article] [--ns <ns>]");
+  std::process::exit(1);
+}
+
+fn main() {
+  let args: Vec<String> = env::args().collect();
+  if args.len() < 3 {
+    usage();
+  }
+  let format: Format = args[1].parse().unwrap_or_else(|e| {
+    eprintln!("{}", e);
+    usage();
+  });
+  let output_path = &args[2];
+
+  let mut input_path = "graph.bin";
+  let mut filter = Filter {
+    exclude_redirects: false,
+    exclude_date_related: false,
+    exclude_list_article: false,
+    ns: None,
+  };
+  let mut rest = args[3..].iter();
+  while let Some(arg) = rest.next() {
+    match arg.as_str() {
+      "--exclude-redirects" => filter.exclude_redirects = true,
+      "--exclude-date-related" => filter.exclude_date_related = true,
+      "--exclude-list-article" => filter.exclude_list_article = true,
+      "--ns" => filter.ns = Some(rest.next().and_then(|ns| ns.parse().ok()).unwrap_or_else(|| usage())),
+      _ if !arg.starts_with("--") => input_path = arg,
+      _ => usage(),
+    }
+  }
+
+  println!("Reading graph from: {}", input_path);
+  let graph = Arc::new(read_graph(input_path));
+
+  println!("Exporting graph to: {}", output_path);
+  if filter.is_empty() {
+    Exporter::new(graph).export(format, output_path).unwrap();
+  } else {
+    let subgraph = graphipedia::graph::Subgraph::new(graph, |graph, index| filter.keep(graph, index));
+    println!("Keeping {} nodes", subgraph.node_count());
+    Exporter::new(Arc::new(subgraph)).export(format, output_path).unwrap();
+  }
+  println!("Exporting graph done");
+}
@@ -30,7 +30,7 @@ fn get_title_to_id_map(path: &str, total_pages: u64) -> HashMap<String, u32> {
     .collect();
   progress_bar.finish_with_message("Read XML file done");
 
-  return title_to_id_map
+  title_to_id_map
 }
 
 fn get_scraped_pages(path: &str, title_to_id_map: HashMap<String, u32>, total_pages: u64) -> Vec<graphipedia::wikipedia_page_scraper::Page> {
@@ -47,7 +47,7 @@ fn get_scraped_pages(path: &str, title_to_id_map: HashMap<String, u32>, total_pa
         .progress_chars("#>-"),
   );
 
-  let scraped_pages = parser.iter().par_bridge()
+  let mut scraped_pages: Vec<graphipedia::wikipedia_page_scraper::Page> = parser.iter().par_bridge()
     .map(|page| {
       let page = page.unwrap();
       progress_bar.inc(1);
@@ -56,10 +56,17 @@ fn get_scraped_pages(path: &str, title_to_id_map: HashMap<String, u32>, total_pa
     .collect();
   progress_bar.finish_with_message("Read XML file done");
 
+  // `par_bridge` hands pages back in whatever order the threads finish
+  // them; sorting keeps node indices the same from one run to the next.
+  scraped_pages.par_sort_unstable_by_key(|page| page.id);
+
   scraped_pages
 }
 
-fn generate_links(pages: &Vec<graphipedia::wikipedia_page_scraper::Page>) -> (HashMap<usize, Vec<usize>>, HashMap<usize, Vec<usize>>) {
+type Links = HashMap<usize, Vec<usize>>;
+type LinkAttributes = HashMap<usize, Vec<graphipedia::graph::EdgeAttribute>>;
+
+fn generate_links(pages: &[graphipedia::wikipedia_page_scraper::Page]) -> (Links, Links, LinkAttributes) {
   println!("Generating links...");
   let id_to_index: std::collections::HashMap<u32, usize> = pages.iter()
     .enumerate()
@@ -68,6 +75,7 @@ fn generate_links(pages: &Vec<graphipedia::wikipedia_page_scraper::Page>) -> (Ha
 
   let mut links_map: HashMap<usize, Vec<usize>> = std::collections::HashMap::new();
   let mut reverse_links_map: HashMap<usize, Vec<usize>> = std::collections::HashMap::new();
+  let mut link_attributes_map: LinkAttributes = std::collections::HashMap::new();
 
   let progress_bar = ProgressBar::new(pages.len() as u64);
   progress_bar.set_style(
@@ -79,20 +87,21 @@ fn generate_links(pages: &Vec<graphipedia::wikipedia_page_scraper::Page>) -> (Ha
 
   for (i, page) in pages.iter().enumerate() {
     progress_bar.inc(1);
-    for linked_page in &page.linked_pages {
+    for (linked_page, &attribute) in page.linked_pages.iter().zip(&page.link_attributes) {
       if let Some(&linked_index) = id_to_index.get(linked_page) {
-        links_map.entry(i).or_insert_with(Vec::new).push(linked_index);
-        reverse_links_map.entry(linked_index).or_insert_with(Vec::new).push(i);
+        links_map.entry(i).or_default().push(linked_index);
+        reverse_links_map.entry(linked_index).or_default().push(i);
+        link_attributes_map.entry(i).or_default().push(attribute);
       }
     }
   }
   progress_bar.finish_with_message("Generating links done");
 
-  (links_map, reverse_links_map)
+  (links_map, reverse_links_map, link_attributes_map)
 }
 
 fn gen_graph(
-  pages: &Vec<graphipedia::wikipedia_page_scraper::Page>,
+  pages: &[graphipedia::wikipedia_page_scraper::Page],
   links: &HashMap<usize, Vec<usize>>,
   reverse_links: &HashMap<usize, Vec<usize>>,
 ) -> graphipedia::graph::Graph {
@@ -138,7 +147,29 @@ fn gen_graph(
 
   progress_bar.finish_with_message("Generating graph done");
 
-  return graph;
+  graph
+}
+
+/// Lays out the link attributes in the order of `graph.forward_edges`.
+fn gen_edge_attributes(
+  graph: &graphipedia::graph::Graph,
+  link_attributes: &LinkAttributes,
+) -> graphipedia::graph::EdgeAttributes {
+  let mut attributes = Vec::with_capacity(graph.forward_edges.len());
+  for index in 0..graph.nodes.len() {
+    attributes.extend_from_slice(link_attributes.get(&index).map(|a| a.as_slice()).unwrap_or(&[]));
+  }
+  graphipedia::graph::EdgeAttributes::new(graph, attributes.into_boxed_slice())
+}
+
+fn export_edge_attributes(edge_attributes: &graphipedia::graph::EdgeAttributes, path: &str) {
+  println!("Exporting edge attributes to: {}", path);
+  use std::fs::File;
+  use std::io::Write;
+  let encoded = bincode::serialize(edge_attributes).unwrap();
+  let mut file = File::create(path).unwrap();
+  file.write_all(&encoded).unwrap();
+  println!("Exporting edge attributes done");
 }
 
 fn export_graph(graph: &graphipedia::graph::Graph, path: &str) {
@@ -169,8 +200,10 @@ fn main() {
   let total_pages = 300_0000;
   let title_to_id_map = get_title_to_id_map(input_path, total_pages);
   let pages = get_scraped_pages(input_path, title_to_id_map, total_pages);
-  let (links, reverse_links) = generate_links(&pages);
+  let (links, reverse_links, link_attributes) = generate_links(&pages);
   let graph = gen_graph(&pages, &links, &reverse_links);
+  let edge_attributes = gen_edge_attributes(&graph, &link_attributes);
 
   export_graph(&graph, output_path);
+  export_edge_attributes(&edge_attributes, &graphipedia::graph::EdgeAttributes::path_for(output_path));
 }
\ No newline at end of file
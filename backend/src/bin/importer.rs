@@ -0,0 +1,48 @@
+use std::env;
+use std::fs::File;
+use std::io::{BufReader, Write};
+
+fn export_graph(graph: &graphipedia::graph::Graph, path: &str) {
+  println!("Exporting graph to: {}", path);
+  let encoded = bincode::serialize(graph).unwrap();
+  let mut file = File::create(path).unwrap();
+  file.write_all(&encoded).unwrap();
+  println!("Exporting graph done");
+}
+
+fn main() {
+  let args: Vec<String> = env::args().collect();
+  if args.len() < 3 {
+    eprintln!("usage: importer <nodes.tsv> <edges.tsv> [graph.bin]");
+    std::process::exit(1);
+  }
+  let nodes_path = &args[1];
+  let edges_path = &args[2];
+  let output_path = if args.len() > 3 {
+    &args[3]
+  } else {
+    "graph.bin"
+  };
+
+  let mut importer = graphipedia::graph_importer::Importer::new();
+
+  println!("Reading nodes from: {}", nodes_path);
+  let nodes = BufReader::new(File::open(nodes_path).unwrap());
+  if let Err(e) = importer.read_nodes(nodes) {
+    eprintln!("{}: {}", nodes_path, e);
+    std::process::exit(1);
+  }
+
+  println!("Reading edges from: {}", edges_path);
+  let edges = BufReader::new(File::open(edges_path).unwrap());
+  if let Err(e) = importer.read_edges(edges) {
+    eprintln!("{}: {}", edges_path, e);
+    std::process::exit(1);
+  }
+
+  let graph = importer.build().unwrap_or_else(|e| {
+    eprintln!("{}", e);
+    std::process::exit(1);
+  });
+  export_graph(&graph, output_path);
+}
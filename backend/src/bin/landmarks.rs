@@ -0,0 +1,53 @@
+use std::env;
+use std::fs::File;
+use std::io::{Read, Write};
+
+fn read_graph(path: &str) -> graphipedia::graph::Graph {
+  let mut file = File::open(path).unwrap();
+  let mut buf = Vec::new();
+  file.read_to_end(&mut buf).unwrap();
+  bincode::deserialize(&buf).unwrap()
+}
+
+fn usage() -> ! {
+  eprintln!("usage: landmarks [graph.bin] [--count <n>] [--selection degree|farthest]");
+  std::process::exit(1);
+}
+
+fn main() {
+  let args: Vec<String> = env::args().collect();
+  let mut graph_path = "graph.bin";
+  let mut count = graphipedia::graph::DistanceOracle::DEFAULT_LANDMARKS;
+  let mut selection = graphipedia::graph::LandmarkSelection::default();
+  let mut rest = args[1..].iter();
+  while let Some(arg) = rest.next() {
+    match arg.as_str() {
+      "--count" => count = rest.next().and_then(|n| n.parse().ok()).unwrap_or_else(|| usage()),
+      "--selection" => selection = match rest.next().map(String::as_str) {
+        Some("degree") => graphipedia::graph::LandmarkSelection::Degree,
+        Some("farthest") => graphipedia::graph::LandmarkSelection::Farthest,
+        _ => usage(),
+      },
+      path if !path.starts_with("--") => graph_path = path,
+      _ => usage(),
+    }
+  }
+
+  println!("Reading graph from: {}", graph_path);
+  let graph = read_graph(graph_path);
+
+  println!("Computing {} landmarks ({:?})", count, selection);
+  let start_time = std::time::Instant::now();
+  let oracle = graphipedia::graph::DistanceOracle::build(&graph, count, selection);
+  println!("Computing landmarks done in {:?}", start_time.elapsed());
+  for &index in oracle.landmarks() {
+    println!("  {}", graph.nodes[index].title);
+  }
+
+  let output_path = graphipedia::graph::DistanceOracle::path_for(graph_path);
+  println!("Exporting landmarks to: {}", output_path);
+  let encoded = bincode::serialize(&oracle).unwrap();
+  let mut file = File::create(&output_path).unwrap();
+  file.write_all(&encoded).unwrap();
+  println!("Exporting landmarks done");
+}
@@ -0,0 +1,65 @@
+use std::env;
+use std::fs::File;
+use std::io::{Read, Write};
+
+fn read_graph(path: &str) -> graphipedia::graph::Graph {
+  let mut file = File::open(path).unwrap();
+  let mut buf = Vec::new();
+  file.read_to_end(&mut buf).unwrap();
+  bincode::deserialize(&buf).unwrap()
+}
+
+fn read_stats(path: &str) -> Option<graphipedia::graph::GraphStats> {
+  let file = File::open(path).ok()?;
+  serde_json::from_reader(std::io::BufReader::new(file)).ok()
+}
+
+fn usage() -> ! {
+  eprintln!("usage: stats [graph.bin] [--top <n>] [--refresh]");
+  std::process::exit(1);
+}
+
+fn main() {
+  let args: Vec<String> = env::args().collect();
+  let mut graph_path = "graph.bin";
+  let mut top = graphipedia::graph::GraphStats::DEFAULT_TOP;
+  let mut refresh = false;
+  let mut rest = args[1..].iter();
+  while let Some(arg) = rest.next() {
+    match arg.as_str() {
+      "--top" => top = rest.next().and_then(|n| n.parse().ok()).unwrap_or_else(|| usage()),
+      "--refresh" => refresh = true,
+      path if !path.starts_with("--") => graph_path = path,
+      _ => usage(),
+    }
+  }
+
+  println!("Reading graph from: {}", graph_path);
+  let graph = read_graph(graph_path);
+
+  // Cached stats are reused unless they are for another graph or a
+  // different number of hubs was asked for.
+  let stats_path = graphipedia::graph::GraphStats::path_for(graph_path);
+  let cached = read_stats(&stats_path).filter(|stats| {
+    !refresh && stats.matches(&graph) && stats.top_forward.len() == top.min(stats.nodes.total - stats.nodes.redirect)
+  });
+  let stats = match cached {
+    Some(stats) => {
+      println!("Using stats from: {}", stats_path);
+      stats
+    },
+    None => {
+      println!("Computing stats");
+      let stats = graphipedia::graph::GraphStats::compute(&graph, top);
+      println!("Computing stats done in {:?}", stats.duration);
+      let mut file = File::create(&stats_path).unwrap();
+      serde_json::to_writer_pretty(&mut file, &stats).unwrap();
+      file.write_all(b"\n").unwrap();
+      println!("Wrote stats to: {}", stats_path);
+      stats
+    },
+  };
+
+  println!();
+  print!("{}", stats);
+}
@@ -0,0 +1,139 @@
+use rayon::prelude::*;
+use std::env;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read, Write};
+
+fn read_graph(path: &str) -> graphipedia::graph::Graph {
+  let mut file = File::open(path).unwrap();
+  let mut buf = Vec::new();
+  file.read_to_end(&mut buf).unwrap();
+  bincode::deserialize(&buf).unwrap()
+}
+
+/// `None` for files in an older format, which are then not carried over.
+fn read_edge_attributes(path: &str) -> Option<graphipedia::graph::EdgeAttributes> {
+  let mut file = File::open(path).unwrap();
+  let mut buf = Vec::new();
+  file.read_to_end(&mut buf).unwrap();
+  bincode::deserialize(&buf).ok()
+}
+
+fn register_pages(updater: &mut graphipedia::graph_updater::Updater, path: &str) {
+  println!("Reading titles from XML file: {}", path);
+  let mut reader = quick_xml::Reader::from_file(path).unwrap();
+  let mut parser = graphipedia::wikipedia_xml_parser::Parser::new(&mut reader);
+  for page in parser.iter() {
+    updater.register(&page.unwrap());
+  }
+}
+
+fn apply_pages(
+  updater: &mut graphipedia::graph_updater::Updater,
+  scraper: &graphipedia::wikipedia_page_scraper::Scraper,
+  path: &str,
+) {
+  println!("Reading pages from XML file: {}", path);
+  let mut reader = quick_xml::Reader::from_file(path).unwrap();
+  let mut parser = graphipedia::wikipedia_xml_parser::Parser::new(&mut reader);
+  let scraped_pages: Vec<_> = parser.iter().par_bridge()
+    .map(|page| scraper.scrape(&page.unwrap()))
+    .collect();
+  for page in scraped_pages {
+    updater.apply(page);
+  }
+}
+
+fn read_deleted(updater: &mut graphipedia::graph_updater::Updater, path: &str) {
+  println!("Reading deleted page ids from: {}", path);
+  let file = BufReader::new(File::open(path).unwrap());
+  for line in file.lines() {
+    let line = line.unwrap();
+    let line = line.trim();
+    if line.is_empty() || line.starts_with('#') {
+      continue;
+    }
+    updater.delete(line.parse().unwrap());
+  }
+}
+
+fn export_graph(graph: &graphipedia::graph::Graph, path: &str) {
+  println!("Exporting graph to: {}", path);
+  let encoded = bincode::serialize(graph).unwrap();
+  let mut file = File::create(path).unwrap();
+  file.write_all(&encoded).unwrap();
+  println!("Exporting graph done");
+}
+
+fn export_edge_attributes(edge_attributes: &graphipedia::graph::EdgeAttributes, path: &str) {
+  println!("Exporting edge attributes to: {}", path);
+  let encoded = bincode::serialize(edge_attributes).unwrap();
+  let mut file = File::create(path).unwrap();
+  file.write_all(&encoded).unwrap();
+  println!("Exporting edge attributes done");
+}
+
+fn export_summary(summary: &graphipedia::graph_updater::UpdateSummary, path: &str) {
+  println!("Exporting update summary to: {}", path);
+  let file = File::create(path).unwrap();
+  serde_json::to_writer_pretty(file, summary).unwrap();
+}
+
+fn main() {
+  let args: Vec<String> = env::args().collect();
+  if args.len() < 3 {
+    eprintln!("usage: updater <graph.bin> <changes.xml>... [--deleted <ids.txt>]");
+    std::process::exit(1);
+  }
+  let input_path = &args[1];
+  // `graph.bin` is updated to `graph.v1.bin`, `graph.v1.bin` to `graph.v2.bin`.
+  let output_path = graphipedia::graph_updater::Updater::path_for(input_path);
+
+  let mut change_paths = Vec::new();
+  let mut deleted_paths = Vec::new();
+  let mut rest = args[2..].iter();
+  while let Some(arg) = rest.next() {
+    if arg == "--deleted" {
+      deleted_paths.push(rest.next().expect("--deleted requires a path"));
+    } else {
+      change_paths.push(arg);
+    }
+  }
+
+  println!("Reading graph from: {}", input_path);
+  let mut updater = graphipedia::graph_updater::Updater::new(read_graph(input_path));
+  let edge_attributes_path = graphipedia::graph::EdgeAttributes::path_for(input_path);
+  if std::path::Path::new(&edge_attributes_path).exists() {
+    match read_edge_attributes(&edge_attributes_path) {
+      Some(edge_attributes) => updater = updater.with_edge_attributes(edge_attributes),
+      None => eprintln!("Ignoring unreadable edge attributes: {}", edge_attributes_path),
+    }
+  }
+
+  for path in &deleted_paths {
+    read_deleted(&mut updater, path);
+  }
+  for path in &change_paths {
+    register_pages(&mut updater, path);
+  }
+
+  let scraper = graphipedia::wikipedia_page_scraper::Scraper::new(updater.title_to_id_map());
+  for path in &change_paths {
+    apply_pages(&mut updater, &scraper, path);
+  }
+
+  let (graph, edge_attributes, summary) = updater.build();
+  println!(
+    "Pages: {} added, {} changed, {} deleted, {} unchanged",
+    summary.added_pages, summary.changed_pages, summary.deleted_pages, summary.unchanged_pages,
+  );
+  println!("Edges: {} -> {}", summary.old_edge_count, summary.new_edge_count);
+
+  export_graph(&graph, &output_path);
+  match edge_attributes {
+    Some(edge_attributes) => {
+      export_edge_attributes(&edge_attributes, &graphipedia::graph::EdgeAttributes::path_for(&output_path));
+    },
+    None => println!("No edge attributes to carry over; weighted search only applies hub penalties"),
+  }
+  export_summary(&summary, &graphipedia::graph_updater::UpdateSummary::path_for(&output_path));
+}
@@ -0,0 +1,27 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// One bit per node that threads can set concurrently, used to let exactly
+/// one thread claim a node during a parallel frontier expansion.
+pub(crate) struct AtomicBitmap {
+  words: Box<[AtomicU64]>,
+}
+
+impl AtomicBitmap {
+  pub(crate) fn new(len: usize) -> Self {
+    AtomicBitmap { words: (0..len.div_ceil(64)).map(|_| AtomicU64::new(0)).collect() }
+  }
+
+  /// Sets the bit, returning whether this call was the one that set it.
+  #[inline]
+  pub(crate) fn claim(&self, index: usize) -> bool {
+    let mask = 1 << (index % 64);
+    self.words[index / 64].fetch_or(mask, Ordering::Relaxed) & mask == 0
+  }
+
+  /// Unsets the bit. Claimed nodes are released one by one, which keeps
+  /// resetting proportional to what was claimed.
+  #[inline]
+  pub(crate) fn release(&mut self, index: usize) {
+    *self.words[index / 64].get_mut() &= !(1 << (index % 64));
+  }
+}
@@ -0,0 +1,155 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use crate::graph::GraphView;
+use crate::graph::{CancellationToken, DistanceOracle, SearchWorkspacePool};
+use crate::graph::{Budget, GraphSearch, GraphSearchQuery, GraphSearchResult, GraphSearchResultNode, ShortestPathDag};
+
+/// Many `GraphSearch`es at once, spread over threads and keeping only a
+/// summary per query. Workspaces come from a shared pool, so each thread
+/// reuses the same few buffers from one query to the next.
+pub struct BatchSearch<G: GraphView = crate::graph::Graph> {
+  graph: Arc<G>,
+  title_to_index: Arc<HashMap<String, usize>>,
+  id_to_index: Arc<HashMap<u32, usize>>,
+  oracle: Option<Arc<DistanceOracle>>,
+  cancellation: Option<CancellationToken>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub struct BatchSearchQuery {
+  /// Also return one shortest path per query.
+  pub sample_path: Option<bool>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct BatchSearchItem {
+  pub start: String,
+  pub end: String,
+  pub start_not_found: bool,
+  pub end_not_found: bool,
+  pub route_found: bool,
+  pub distance: Option<u32>,
+  pub path_count: u64,
+  pub path_count_saturated: bool,
+  pub path: Option<Vec<GraphSearchResultNode>>,
+  pub budget_exhausted: Option<Budget>,
+  pub duration: std::time::Duration,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct BatchSearchResult {
+  /// In the order of the queries.
+  pub items: Vec<BatchSearchItem>,
+  pub route_found: usize,
+  pub duration: std::time::Duration,
+}
+
+impl BatchSearch {
+  pub const MAX_QUERIES: usize = 10_000;
+}
+
+impl<G: GraphView> BatchSearch<G> {
+  pub fn new(graph: Arc<G>, title_to_index: Arc<HashMap<String, usize>>, id_to_index: Arc<HashMap<u32, usize>>) -> Self {
+    BatchSearch { graph, title_to_index, id_to_index, oracle: None, cancellation: None }
+  }
+
+  /// See `GraphSearch::with_oracle`.
+  pub fn with_oracle(mut self, oracle: Option<Arc<DistanceOracle>>) -> Self {
+    self.oracle = oracle;
+    self
+  }
+
+  /// Stops every remaining search once `cancellation` is cancelled.
+  pub fn with_cancellation(mut self, cancellation: CancellationToken) -> Self {
+    self.cancellation = Some(cancellation);
+    self
+  }
+
+  /// Runs the queries in parallel on the current thread pool.
+  pub fn exec(
+    &self,
+    queries: Vec<GraphSearchQuery>,
+    batch_query: &BatchSearchQuery,
+    workspaces: &SearchWorkspacePool,
+  ) -> BatchSearchResult {
+    let start_time = std::time::Instant::now();
+    let items = queries
+      .into_par_iter()
+      .map(|query| self.search(query, batch_query, workspaces))
+      .collect();
+    BatchSearchResult::new(items, start_time.elapsed())
+  }
+
+  /// Runs one query of a batch on the calling thread.
+  pub fn search(
+    &self,
+    query: GraphSearchQuery,
+    batch_query: &BatchSearchQuery,
+    workspaces: &SearchWorkspacePool,
+  ) -> BatchSearchItem {
+    let (start, end) = (query.start.clone(), query.end.clone());
+    let mut graph_search = GraphSearch::with_workspace(
+      self.graph.clone(),
+      self.title_to_index.clone(),
+      self.id_to_index.clone(),
+      query,
+      workspaces.take(),
+    )
+    .with_oracle(self.oracle.clone());
+    if let Some(cancellation) = &self.cancellation {
+      graph_search = graph_search.with_cancellation(cancellation.clone());
+    }
+
+    let result = graph_search.exec();
+    workspaces.put(graph_search.into_workspace());
+    BatchSearchItem::new(start, end, &result, batch_query.sample_path.unwrap_or(false))
+  }
+}
+
+impl BatchSearchResult {
+  /// `items` in the order of the queries.
+  pub fn new(items: Vec<BatchSearchItem>, duration: std::time::Duration) -> Self {
+    BatchSearchResult {
+      route_found: items.iter().filter(|item| item.route_found).count(),
+      items,
+      duration,
+    }
+  }
+}
+
+impl BatchSearchItem {
+  fn new(start: String, end: String, result: &GraphSearchResult, sample_path: bool) -> Self {
+    let dag = ShortestPathDag::new(result);
+    let path = dag.as_ref().filter(|_| sample_path).and_then(|dag| {
+      let path = dag.paths(0, 1).into_iter().next()?;
+      Some(path.into_iter().map(|index| dag.node(index).clone()).collect())
+    });
+
+    // The start and end being the same page is a route of length 0.
+    let distance = if result.is_start_end_some {
+      Some(0)
+    } else {
+      result.end_node.as_ref().filter(|_| result.route_found).map(|node| node.distance)
+    };
+    let path_count = match &dag {
+      Some(dag) => dag.path_count(),
+      None => result.is_start_end_some as u64,
+    };
+
+    BatchSearchItem {
+      start,
+      end,
+      start_not_found: result.start_not_found,
+      end_not_found: result.end_not_found,
+      route_found: result.route_found,
+      distance,
+      path_count,
+      path_count_saturated: dag.as_ref().is_some_and(|dag| dag.is_path_count_saturated()),
+      path,
+      budget_exhausted: result.budget_exhausted,
+      duration: result.duration,
+    }
+  }
+}
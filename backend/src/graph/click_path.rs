@@ -0,0 +1,260 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use serde::{Deserialize, Serialize};
+use crate::graph::GraphView;
+use crate::graph::{CancellationToken, DistanceMap, SearchWorkspace};
+use crate::graph::{GraphSearch, GraphSearchQuery, GraphSearchResultNode};
+use crate::graph::graph_search::{resolve_pages, resolve_redirect};
+
+/// Checks the pages a player clicked through in the Wikipedia game against
+/// the graph: whether every click follows a real link, how the number of
+/// clicks compares with the shortest route, and where the player first
+/// strayed from every shortest route.
+///
+/// A page stands for the page its redirect leads to, and a link to a
+/// redirect counts as a link to its target, as redirects are crossed for
+/// free in `GraphSearch`.
+pub struct ClickPath<G: GraphView = crate::graph::Graph> {
+  graph: Arc<G>,
+  title_to_index: Arc<HashMap<String, usize>>,
+  id_to_index: Arc<HashMap<u32, usize>>,
+  query: ClickPathQuery,
+  graph_search: GraphSearch<G>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub struct ClickPathQuery {
+  /// `|`-separated pages in the order they were visited, starting with the
+  /// start page, as titles or as `#<page id>`.
+  pub path: String,
+  /// The page to reach.
+  pub end: String,
+  pub enable_date_related: Option<bool>,
+  pub enable_list_article: Option<bool>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ClickPathResult {
+  pub steps: Vec<ClickPathStep>,
+  pub end_not_found: bool,
+  pub clicks: usize,
+  /// Set when the path had more than `MAX_STEPS` pages; only the first
+  /// ones are checked.
+  pub truncated: bool,
+  /// Fewest clicks from the start to the end, `None` when there is no route.
+  pub optimal_distance: Option<u32>,
+  /// Every page exists and every click follows a link.
+  pub valid: bool,
+  pub reached_end: bool,
+  /// Index of the first step off every shortest route, `None` when the
+  /// player never left them.
+  pub deviation: Option<usize>,
+  /// A shortest route from the last step still on a shortest route to the
+  /// end, starting with that step's page. Distances count from the start.
+  pub continuation: Vec<GraphSearchResultNode>,
+  pub duration: std::time::Duration,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ClickPathStep {
+  pub title: String,
+  /// The page, with the number of clicks it took to get there as distance.
+  pub node: Option<GraphSearchResultNode>,
+  pub not_found: bool,
+  /// Whether the previous page links to this one; always true for the
+  /// start.
+  pub is_link: bool,
+  /// Fewest clicks left to the end from here.
+  pub remaining: Option<u32>,
+  pub on_shortest_path: bool,
+}
+
+impl ClickPathQuery {
+  pub const MAX_STEPS: usize = 1000;
+}
+
+impl<G: GraphView> ClickPath<G> {
+  pub fn with_workspace(
+    graph: Arc<G>,
+    title_to_index: Arc<HashMap<String, usize>>,
+    id_to_index: Arc<HashMap<u32, usize>>,
+    query: ClickPathQuery,
+    workspace: SearchWorkspace,
+  ) -> Self {
+    let search_query = GraphSearchQuery {
+      end: query.end.clone(),
+      enable_date_related: query.enable_date_related,
+      enable_list_article: query.enable_list_article,
+      ..Default::default()
+    };
+    let graph_search = GraphSearch::with_workspace(graph.clone(), title_to_index.clone(), id_to_index.clone(), search_query, workspace);
+
+    ClickPath { graph, title_to_index, id_to_index, query, graph_search }
+  }
+
+  /// See `GraphSearch::with_cancellation`.
+  pub fn with_cancellation(mut self, cancellation: CancellationToken) -> Self {
+    self.graph_search = self.graph_search.with_cancellation(cancellation);
+    self
+  }
+
+  pub fn exec(&mut self) -> ClickPathResult {
+    let start_time = std::time::Instant::now();
+    let graph = self.graph.clone();
+    let graph = graph.as_ref();
+
+    let mut pages: Vec<&str> = self.query.path
+      .split('|')
+      .map(str::trim)
+      .filter(|page| !page.is_empty())
+      .collect();
+    let truncated = pages.len() > ClickPathQuery::MAX_STEPS;
+    pages.truncate(ClickPathQuery::MAX_STEPS);
+    let pages: Vec<(String, Option<usize>)> = pages
+      .into_iter()
+      .map(|page| {
+        let (found, _) = resolve_pages(&self.title_to_index, &self.id_to_index, Some(page));
+        (page.to_string(), found.first().copied())
+      })
+      .collect();
+    let (ends, _) = resolve_pages(&self.title_to_index, &self.id_to_index, Some(&self.query.end));
+    let ends: Vec<usize> = ends.into_iter().map(|index| resolve_redirect(graph, index)).collect();
+
+    // Distances to the end for every page that can reach it.
+    let (_, back_distance_map, _) = self.graph_search.map_reachable();
+    let remaining = |index: usize| Some(back_distance_map.get(index)).filter(|_| back_distance_map.is_visited(index));
+
+    let optimal_distance = pages.first().and_then(|&(_, index)| remaining(resolve_redirect(graph, index?)));
+    let mut steps: Vec<ClickPathStep> = Vec::with_capacity(pages.len());
+    let mut previous = None;
+    let mut deviation = None;
+    let mut last_on_route = None;
+    for (i, (title, index)) in pages.into_iter().enumerate() {
+      let index = index.map(|index| resolve_redirect(graph, index));
+      let is_link = i == 0 || previous.zip(index).is_some_and(|(previous, index)| {
+        graph.forward_neighbors(previous).any(|neighbor| resolve_redirect(graph, neighbor) == index)
+      });
+      let step_remaining = index.and_then(remaining);
+      let on_shortest_path = deviation.is_none()
+        && is_link
+        && optimal_distance.zip(step_remaining).is_some_and(|(optimal, left)| left as usize + i == optimal as usize);
+      if on_shortest_path {
+        last_on_route = index.map(|index| (i, index));
+      } else if deviation.is_none() {
+        deviation = Some(i);
+      }
+
+      steps.push(ClickPathStep {
+        title,
+        node: index.map(|index| GraphSearchResultNode::new(graph, index, i as u32)),
+        not_found: index.is_none(),
+        is_link,
+        remaining: step_remaining,
+        on_shortest_path,
+      });
+      previous = index;
+    }
+
+    let continuation = match last_on_route {
+      Some((i, index)) => continuation(graph, back_distance_map, index, i as u32),
+      None => Vec::new(),
+    };
+    let reached_end = previous.is_some_and(|index| ends.contains(&index));
+
+    ClickPathResult {
+      valid: steps.iter().all(|step| !step.not_found && step.is_link),
+      clicks: steps.len().saturating_sub(1),
+      truncated,
+      steps,
+      end_not_found: ends.is_empty(),
+      optimal_distance,
+      reached_end,
+      deviation,
+      continuation,
+      duration: start_time.elapsed(),
+    }
+  }
+
+  /// Hands the workspace back, e.g. to return it to its pool.
+  pub fn into_workspace(self) -> SearchWorkspace {
+    self.graph_search.into_workspace()
+  }
+}
+
+/// Follows links that bring the end one click closer, from `from` until
+/// the end, with redirects crossed for free. A page is never entered
+/// twice, so a cycle of redirects ends the walk.
+fn continuation<G: GraphView>(
+  graph: &G,
+  back_distance_map: &DistanceMap,
+  from: usize,
+  clicks: u32,
+) -> Vec<GraphSearchResultNode> {
+  let left = back_distance_map.get(from);
+  let mut nodes = vec![GraphSearchResultNode::new(graph, from, clicks)];
+  let mut current = from;
+  let mut seen = HashSet::from([from]);
+  while back_distance_map.get(current) > 0 || graph.is_redirect(current) {
+    let cost = if graph.is_redirect(current) { 0 } else { 1 };
+    let next = graph.forward_neighbors(current).find(|&neighbor| {
+      back_distance_map.is_visited(neighbor)
+        && back_distance_map.get(neighbor) + cost == back_distance_map.get(current)
+        && !seen.contains(&neighbor)
+    });
+    let Some(next) = next else {
+      break;
+    };
+    nodes.push(GraphSearchResultNode::new(graph, next, clicks + left - back_distance_map.get(next)));
+    seen.insert(next);
+    current = next;
+  }
+  nodes
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::graph::test_graph::TestGraph;
+
+  fn click_path(graph: &TestGraph, path: &str, end: &str) -> ClickPathResult {
+    let query = ClickPathQuery { path: path.to_string(), end: end.to_string(), ..Default::default() };
+    let workspace = SearchWorkspace::new(graph.graph.node_count());
+    ClickPath::with_workspace(graph.graph.clone(), graph.title_to_index.clone(), graph.id_to_index.clone(), query, workspace)
+      .exec()
+  }
+
+  /// A -> B -> C -> E, and A -> R (redirect) -> E.
+  fn game() -> TestGraph {
+    TestGraph::new(
+      &[("A", false), ("B", false), ("R", true), ("C", false), ("E", false)],
+      &[vec![1, 2], vec![3], vec![4], vec![4], vec![]],
+    )
+  }
+
+  #[test]
+  fn detour_by_title_and_id() {
+    let result = click_path(&game(), "#1|#2|C|E", "E");
+    assert!(result.valid && result.reached_end && !result.truncated);
+    assert_eq!(result.clicks, 3);
+    assert_eq!(result.optimal_distance, Some(1));
+    assert_eq!(result.deviation, Some(1));
+    let continuation: Vec<&str> = result.continuation.iter().map(|node| node.title.as_str()).collect();
+    assert_eq!(continuation, vec!["A", "R", "E"]);
+  }
+
+  #[test]
+  fn click_through_redirect() {
+    let result = click_path(&game(), "A|E", "R");
+    assert!(result.valid && result.reached_end);
+    assert_eq!(result.deviation, None);
+    assert!(result.steps.iter().all(|step| step.on_shortest_path));
+  }
+
+  #[test]
+  fn long_path_is_truncated() {
+    let path = vec!["A"; ClickPathQuery::MAX_STEPS + 1].join("|");
+    let result = click_path(&game(), &path, "E");
+    assert!(result.truncated);
+    assert_eq!(result.steps.len(), ClickPathQuery::MAX_STEPS);
+  }
+}
@@ -0,0 +1,166 @@
+/// Distance reported for nodes that have not been reached.
+pub const UNVISITED: u32 = u32::MAX;
+
+/// Per-node hop distances that start out as one byte per node and widen to
+/// `u16` and then `u32` the first time a distance does not fit, so short
+/// searches on large graphs stay small while long chains stay correct.
+///
+/// Every node that gets a distance is remembered, so `clear` only resets
+/// the visited region and a map can be reused across searches.
+pub struct DistanceMap {
+  storage: Storage,
+  touched: Vec<usize>,
+}
+
+enum Storage {
+  U8(Vec<u8>),
+  U16(Vec<u16>),
+  U32(Vec<u32>),
+}
+
+/// `distance + 1`, or `None` when it would collide with `UNVISITED`.
+pub fn next_distance(distance: u32) -> Option<u32> {
+  distance.checked_add(1).filter(|&d| d != UNVISITED)
+}
+
+impl DistanceMap {
+  pub fn new(node_count: usize) -> Self {
+    DistanceMap { storage: Storage::U8(vec![u8::MAX; node_count]), touched: Vec::new() }
+  }
+
+  pub fn len(&self) -> usize {
+    match &self.storage {
+      Storage::U8(v) => v.len(),
+      Storage::U16(v) => v.len(),
+      Storage::U32(v) => v.len(),
+    }
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.len() == 0
+  }
+
+  /// Distance of a node, or `UNVISITED`.
+  #[inline]
+  pub fn get(&self, index: usize) -> u32 {
+    match &self.storage {
+      Storage::U8(v) => if v[index] == u8::MAX { UNVISITED } else { v[index] as u32 },
+      Storage::U16(v) => if v[index] == u16::MAX { UNVISITED } else { v[index] as u32 },
+      Storage::U32(v) => v[index],
+    }
+  }
+
+  #[inline]
+  pub fn is_visited(&self, index: usize) -> bool {
+    self.get(index) != UNVISITED
+  }
+
+  /// Sets the distance of a node, widening the storage if needed.
+  #[inline]
+  pub fn set(&mut self, index: usize, distance: u32) {
+    if !self.is_visited(index) {
+      self.touched.push(index);
+    }
+    self.store(index, distance);
+  }
+
+  /// Nodes that have a distance, in the order they first got one.
+  pub fn visited(&self) -> &[usize] {
+    &self.touched
+  }
+
+  /// Marks every node unvisited again, in time proportional to the number
+  /// of visited nodes.
+  pub fn clear(&mut self) {
+    for i in 0..self.touched.len() {
+      let index = self.touched[i];
+      match &mut self.storage {
+        Storage::U8(v) => v[index] = u8::MAX,
+        Storage::U16(v) => v[index] = u16::MAX,
+        Storage::U32(v) => v[index] = UNVISITED,
+      }
+    }
+    self.touched.clear();
+  }
+
+  #[inline]
+  fn store(&mut self, index: usize, distance: u32) {
+    match &mut self.storage {
+      Storage::U8(v) if distance < u8::MAX as u32 => v[index] = distance as u8,
+      Storage::U16(v) if distance < u16::MAX as u32 => v[index] = distance as u16,
+      Storage::U32(v) => v[index] = distance,
+      _ => {
+        self.widen(distance);
+        self.store(index, distance);
+      },
+    }
+  }
+
+  fn widen(&mut self, distance: u32) {
+    self.storage = match &self.storage {
+      Storage::U8(v) if distance < u16::MAX as u32 => {
+        Storage::U16(v.iter().map(|&d| if d == u8::MAX { u16::MAX } else { d as u16 }).collect())
+      },
+      Storage::U8(v) => {
+        Storage::U32(v.iter().map(|&d| if d == u8::MAX { UNVISITED } else { d as u32 }).collect())
+      },
+      Storage::U16(v) => {
+        Storage::U32(v.iter().map(|&d| if d == u16::MAX { UNVISITED } else { d as u32 }).collect())
+      },
+      Storage::U32(_) => return,
+    };
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn widens_past_byte_and_u16_distances() {
+    let mut map = DistanceMap::new(4);
+    map.set(0, 3);
+    map.set(1, 254);
+    assert!(matches!(map.storage, Storage::U8(_)));
+
+    map.set(2, 255);
+    assert!(matches!(map.storage, Storage::U16(_)));
+    map.set(3, 70_000);
+    assert!(matches!(map.storage, Storage::U32(_)));
+
+    assert_eq!((0..4).map(|index| map.get(index)).collect::<Vec<_>>(), vec![3, 254, 255, 70_000]);
+  }
+
+  #[test]
+  fn widens_straight_to_u32() {
+    let mut map = DistanceMap::new(2);
+    map.set(0, 1);
+    map.set(1, u16::MAX as u32);
+    assert!(matches!(map.storage, Storage::U32(_)));
+    assert_eq!((map.get(0), map.get(1)), (1, u16::MAX as u32));
+  }
+
+  #[test]
+  fn clear_resets_only_visited_nodes() {
+    let mut map = DistanceMap::new(5);
+    map.set(3, 1);
+    map.set(1, 2);
+    map.set(3, 0);
+    assert_eq!(map.visited(), &[3, 1]);
+
+    map.set(4, 300);
+    map.clear();
+    assert!(map.visited().is_empty());
+    assert!((0..5).all(|index| !map.is_visited(index)));
+
+    map.set(2, 7);
+    assert_eq!((map.get(2), map.visited()), (7, &[2][..]));
+  }
+
+  #[test]
+  fn next_distance_stops_before_unvisited() {
+    assert_eq!(next_distance(0), Some(1));
+    assert_eq!(next_distance(UNVISITED - 2), Some(UNVISITED - 1));
+    assert_eq!(next_distance(UNVISITED - 1), None);
+  }
+}
@@ -0,0 +1,283 @@
+use std::collections::{HashMap, VecDeque};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use crate::graph::GraphView;
+use crate::graph::GraphSearchResultNode;
+use crate::graph::graph_search::resolve_pages;
+
+/// Distance estimates from precomputed distances to and from a few
+/// landmark pages.
+///
+/// Distances are counted like `GraphSearch` counts them along links, with
+/// hops out of redirects free, on the whole graph without filters. By the
+/// triangle inequality every landmark gives a lower bound, and a route
+/// through a landmark gives an upper bound.
+#[derive(Serialize, Deserialize)]
+pub struct DistanceOracle {
+  node_count: usize,
+  edge_count: usize,
+  /// `GraphView::fingerprint` of the graph it was built for.
+  fingerprint: u64,
+  landmarks: Vec<usize>,
+  /// `forward[l * node_count + v]` is the distance from landmark `l` to `v`.
+  forward: Box<[u8]>,
+  /// `backward[l * node_count + v]` is the distance from `v` to landmark `l`.
+  backward: Box<[u8]>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum LandmarkSelection {
+  /// The pages with the most links.
+  #[default]
+  Degree,
+  /// Each landmark as far as possible from the ones before it, starting
+  /// from the page with the most links.
+  Farthest,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DistanceBounds {
+  pub lower: u32,
+  /// `None` when no landmark lies on a route.
+  pub upper: Option<u32>,
+  /// Set when a landmark proves there is no route at all.
+  pub unreachable: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub struct DistanceBoundsQuery {
+  /// A title or `#<page id>`.
+  pub start: String,
+  pub end: String,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct DistanceBoundsResult {
+  pub start_not_found: bool,
+  pub end_not_found: bool,
+  pub start_node: Option<GraphSearchResultNode>,
+  pub end_node: Option<GraphSearchResultNode>,
+  pub bounds: Option<DistanceBounds>,
+  /// Set when the bounds meet, so the distance is known exactly.
+  pub exact: bool,
+  pub duration: std::time::Duration,
+}
+
+/// Stored for distances of this or more, which only bound from below.
+const FAR: u8 = u8::MAX - 1;
+const UNREACHABLE: u8 = u8::MAX;
+
+impl DistanceOracle {
+  pub const DEFAULT_LANDMARKS: usize = 16;
+
+  /// `graph.bin` becomes `graph.landmarks.bin`.
+  pub fn path_for(graph_path: &str) -> String {
+    match graph_path.strip_suffix(".bin") {
+      Some(stem) => format!("{}.landmarks.bin", stem),
+      None => format!("{}.landmarks.bin", graph_path),
+    }
+  }
+
+  /// Picks `count` landmarks among non-redirect pages and runs a forward and
+  /// a backward search from each.
+  pub fn build<G: GraphView + Sync>(graph: &G, count: usize, selection: LandmarkSelection) -> Self {
+    let node_count = graph.node_count();
+    let mut candidates: Vec<usize> = (0..node_count).filter(|&index| !graph.is_redirect(index)).collect();
+    candidates.sort_by_key(|&index| {
+      (std::cmp::Reverse(graph.forward_degree(index) + graph.backward_degree(index)), index)
+    });
+    let count = count.min(candidates.len());
+
+    let mut landmarks = Vec::with_capacity(count);
+    let mut forward = Vec::with_capacity(count * node_count);
+    let mut backward = Vec::with_capacity(count * node_count);
+    match selection {
+      LandmarkSelection::Degree => {
+        landmarks.extend_from_slice(&candidates[..count]);
+        let distances: Vec<(Vec<u8>, Vec<u8>)> = landmarks
+          .par_iter()
+          .map(|&landmark| (distances(graph, landmark, true), distances(graph, landmark, false)))
+          .collect();
+        for (forward_distances, backward_distances) in distances {
+          forward.extend(forward_distances);
+          backward.extend(backward_distances);
+        }
+      },
+      LandmarkSelection::Farthest => {
+        // Smallest distance between each page and the landmarks so far,
+        // either way.
+        let mut nearest = vec![UNREACHABLE; node_count];
+        let mut next = candidates.first().copied();
+        while let Some(landmark) = next.filter(|_| landmarks.len() < count) {
+          let (forward_distances, backward_distances) = rayon::join(
+            || distances(graph, landmark, true),
+            || distances(graph, landmark, false),
+          );
+          for index in 0..node_count {
+            nearest[index] = nearest[index].min(forward_distances[index]).min(backward_distances[index]);
+          }
+          landmarks.push(landmark);
+          forward.extend(forward_distances);
+          backward.extend(backward_distances);
+
+          // Unconnected pages are skipped; a landmark there bounds little.
+          next = candidates
+            .iter()
+            .copied()
+            .filter(|&index| nearest[index] != 0 && nearest[index] != UNREACHABLE)
+            .max_by_key(|&index| (nearest[index], std::cmp::Reverse(index)));
+        }
+      },
+    }
+
+    DistanceOracle {
+      node_count,
+      edge_count: graph.edge_count(),
+      fingerprint: graph.fingerprint(),
+      landmarks,
+      forward: forward.into_boxed_slice(),
+      backward: backward.into_boxed_slice(),
+    }
+  }
+
+  /// Whether this oracle was built for `graph`.
+  pub fn matches<G: GraphView>(&self, graph: &G) -> bool {
+    self.node_count == graph.node_count()
+      && self.edge_count == graph.edge_count()
+      && self.fingerprint == graph.fingerprint()
+  }
+
+  pub fn landmarks(&self) -> &[usize] {
+    &self.landmarks
+  }
+
+  /// Bounds between two pages given by title, with redirects resolved like
+  /// `GraphSearch` resolves its start and end.
+  pub fn query<G: GraphView>(
+    &self,
+    graph: &G,
+    title_to_index: &HashMap<String, usize>,
+    id_to_index: &HashMap<u32, usize>,
+    query: &DistanceBoundsQuery,
+  ) -> DistanceBoundsResult {
+    let start_time = std::time::Instant::now();
+    let resolve = |title: &str| match resolve_pages(title_to_index, id_to_index, Some(title)) {
+      (found, not_found) if found.len() == 1 && not_found.is_empty() => Some(found[0]),
+      _ => None,
+    };
+    let (start, end) = (resolve(&query.start), resolve(&query.end));
+
+    let bounds = start.zip(end).map(|(start, end)| {
+      let seed = |index: usize| graph.redirect_target(index).unwrap_or(index);
+      self.bounds(seed(start), seed(end))
+    });
+    DistanceBoundsResult {
+      start_not_found: start.is_none(),
+      end_not_found: end.is_none(),
+      start_node: start.map(|index| GraphSearchResultNode::new(graph, index, 0)),
+      end_node: end.map(|index| {
+        let distance = bounds.and_then(|bounds| bounds.upper).unwrap_or(crate::graph::UNVISITED);
+        GraphSearchResultNode::new(graph, index, distance)
+      }),
+      bounds,
+      exact: bounds.is_some_and(|bounds| bounds.upper == Some(bounds.lower)),
+      duration: start_time.elapsed(),
+    }
+  }
+
+  /// Bounds on the distance from `from` to `to`, both node indices.
+  pub fn bounds(&self, from: usize, to: usize) -> DistanceBounds {
+    match self.lower_bound(from, to) {
+      None => DistanceBounds { lower: crate::graph::UNVISITED, upper: None, unreachable: true },
+      Some(lower) => DistanceBounds { lower, upper: self.upper_bound(from, to), unreachable: false },
+    }
+  }
+
+  /// A distance from `from` to `to` no larger than the real one, or `None`
+  /// when `to` cannot be reached at all.
+  pub fn lower_bound(&self, from: usize, to: usize) -> Option<u32> {
+    if from == to {
+      return Some(0);
+    }
+    let mut lower = 0;
+    for landmark in 0..self.landmarks.len() {
+      let offset = landmark * self.node_count;
+      // d(from, to) >= d(landmark, to) - d(landmark, from)
+      let (landmark_from, landmark_to) = (self.forward[offset + from], self.forward[offset + to]);
+      if landmark_from < FAR {
+        if landmark_to == UNREACHABLE {
+          return None;
+        }
+        lower = lower.max(landmark_to.saturating_sub(landmark_from) as u32);
+      }
+      // d(from, to) >= d(from, landmark) - d(to, landmark)
+      let (from_landmark, to_landmark) = (self.backward[offset + from], self.backward[offset + to]);
+      if to_landmark < FAR {
+        if from_landmark == UNREACHABLE {
+          return None;
+        }
+        lower = lower.max(from_landmark.saturating_sub(to_landmark) as u32);
+      }
+    }
+    Some(lower)
+  }
+
+  /// The shortest route from `from` to `to` through a landmark, if any.
+  pub fn upper_bound(&self, from: usize, to: usize) -> Option<u32> {
+    if from == to {
+      return Some(0);
+    }
+    (0..self.landmarks.len())
+      .filter_map(|landmark| {
+        let offset = landmark * self.node_count;
+        let (from_landmark, landmark_to) = (self.backward[offset + from], self.forward[offset + to]);
+        (from_landmark < FAR && landmark_to < FAR).then(|| from_landmark as u32 + landmark_to as u32)
+      })
+      .min()
+  }
+}
+
+/// Distances from `landmark` along links, or to it against them, with hops
+/// out of redirects free.
+fn distances<G: GraphView>(graph: &G, landmark: usize, forward: bool) -> Vec<u8> {
+  let mut distances = vec![crate::graph::UNVISITED; graph.node_count()];
+  let mut done = vec![false; graph.node_count()];
+  let mut queue = VecDeque::from([landmark]);
+  distances[landmark] = 0;
+  while let Some(node_index) = queue.pop_front() {
+    if std::mem::replace(&mut done[node_index], true) {
+      continue;
+    }
+    let distance = distances[node_index];
+    let mut relax = |neighbor_index: usize, free: bool| {
+      let neighbor_distance = if free { distance } else { distance + 1 };
+      if neighbor_distance < distances[neighbor_index] {
+        distances[neighbor_index] = neighbor_distance;
+        if free {
+          queue.push_front(neighbor_index);
+        } else {
+          queue.push_back(neighbor_index);
+        }
+      }
+    };
+    if forward {
+      let free = graph.is_redirect(node_index);
+      for neighbor_index in graph.forward_neighbors(node_index) {
+        relax(neighbor_index, free);
+      }
+    } else {
+      for neighbor_index in graph.backward_neighbors(node_index) {
+        relax(neighbor_index, graph.is_redirect(neighbor_index));
+      }
+    }
+  }
+
+  distances
+    .into_iter()
+    .map(|distance| match distance {
+      crate::graph::UNVISITED => UNREACHABLE,
+      distance => distance.min(FAR as u32) as u8,
+    })
+    .collect()
+}
@@ -0,0 +1,56 @@
+use serde::{Serialize, Deserialize};
+use crate::graph::GraphView;
+
+/// Where a link sits in the wikitext of the page it comes from.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct EdgeAttribute {
+  /// Offset of the first occurrence of the link, scaled from the start
+  /// (0) to the end (255) of the page.
+  pub position: u8,
+  /// Whether every occurrence of the link is inside a template, such as a
+  /// navbox or an infobox.
+  pub in_template: bool,
+}
+
+/// Attributes of every link of a `Graph`, parallel to `Graph::forward_edges`.
+///
+/// Kept in a file next to the graph (see `path_for`) so graphs written
+/// without attributes still load.
+#[derive(Serialize, Deserialize)]
+pub struct EdgeAttributes {
+  /// `EdgeAttributes::VERSION` when written; files of other versions are
+  /// not used.
+  pub version: u32,
+  /// `GraphView::fingerprint` of the graph they were written for.
+  pub fingerprint: u64,
+  pub attributes: Box<[EdgeAttribute]>,
+}
+
+impl EdgeAttributes {
+  /// Bumped whenever the layout of the file changes.
+  pub const VERSION: u32 = 2;
+
+  pub fn new<G: GraphView>(graph: &G, attributes: Box<[EdgeAttribute]>) -> Self {
+    EdgeAttributes { version: Self::VERSION, fingerprint: graph.fingerprint(), attributes }
+  }
+
+  /// `graph.bin` becomes `graph.edges.bin`.
+  pub fn path_for(graph_path: &str) -> String {
+    match graph_path.strip_suffix(".bin") {
+      Some(stem) => format!("{}.edges.bin", stem),
+      None => format!("{}.edges.bin", graph_path),
+    }
+  }
+
+  /// Whether these attributes were written for `graph`.
+  pub fn matches<G: GraphView>(&self, graph: &G) -> bool {
+    self.version == Self::VERSION
+      && self.attributes.len() == graph.edge_count()
+      && self.fingerprint == graph.fingerprint()
+  }
+
+  /// Attribute of the `offset`-th link of a node.
+  pub fn get<G: GraphView>(&self, graph: &G, index: usize, offset: usize) -> EdgeAttribute {
+    self.attributes[graph.forward_edge_start(index) + offset]
+  }
+}
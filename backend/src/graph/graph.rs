@@ -5,6 +5,9 @@ pub struct Graph {
   pub nodes: Box<[crate::graph::Node]>,
   pub forward_edges: Box<[usize]>,
   pub backward_edges: Box<[usize]>,
+  /// `GraphView::fingerprint`, computed on first use.
+  #[serde(skip)]
+  pub(crate) fingerprint: std::sync::OnceLock<u64>,
 }
 
 impl Graph {
@@ -17,6 +20,42 @@ impl Graph {
       nodes: vec_nodes.into_boxed_slice(),
       forward_edges: vec_forward_edges.into_boxed_slice(),
       backward_edges: vec_backward_edges.into_boxed_slice(),
+      fingerprint: std::sync::OnceLock::new(),
     }
   }
+
+  /// Builds the forward and backward adjacency from per-node link lists.
+  /// `links[i]` holds the indices node `i` links to, in order; the edge
+  /// ranges already stored in `nodes` are overwritten.
+  pub fn from_links(
+    mut nodes: Vec<crate::graph::Node>,
+    links: &[Vec<usize>],
+  ) -> Self {
+    let mut backward_degrees = vec![0usize; nodes.len()];
+    for &target in links.iter().flatten() {
+      backward_degrees[target] += 1;
+    }
+
+    let mut forward_edges = Vec::with_capacity(backward_degrees.iter().sum());
+    let mut backward_offsets = Vec::with_capacity(nodes.len());
+    let mut backward_edge_start = 0;
+    for (index, node) in nodes.iter_mut().enumerate() {
+      let forward_edge_start = forward_edges.len();
+      forward_edges.extend_from_slice(&links[index]);
+      node.forward_edge_range = (forward_edge_start, forward_edges.len());
+      node.backward_edge_range = (backward_edge_start, backward_edge_start + backward_degrees[index]);
+      backward_offsets.push(backward_edge_start);
+      backward_edge_start += backward_degrees[index];
+    }
+
+    let mut backward_edges = vec![0; forward_edges.len()];
+    for (index, targets) in links.iter().enumerate() {
+      for &target in targets {
+        backward_edges[backward_offsets[target]] = index;
+        backward_offsets[target] += 1;
+      }
+    }
+
+    Graph::new(nodes, forward_edges, backward_edges)
+  }
 }
\ No newline at end of file
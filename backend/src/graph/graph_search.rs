@@ -1,45 +1,196 @@
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
+use rayon::prelude::*;
 use serde::{Deserialize, Serialize};
+use crate::graph::GraphView;
+use crate::graph::DistanceMap;
+use crate::graph::DistanceOracle;
+use crate::graph::CancellationToken;
+use crate::graph::SearchWorkspace;
+use crate::graph::ShortestPathDag;
+use crate::graph::distance_map::next_distance;
 
-pub struct GraphSearch {
-  graph: Arc<crate::graph::Graph>,
+/// Levels whose pages have at least this many links are expanded in
+/// parallel; smaller ones are not worth the overhead.
+const PARALLEL_MIN_EDGES: usize = 1 << 14;
+/// A parallel round goes bottom-up once the frontier is more than
+/// `1 / BOTTOM_UP_ALPHA` of the unvisited pages (Beamer et al.).
+const BOTTOM_UP_ALPHA: usize = 14;
+
+/// Called with the progress of a search after every level.
+type ProgressCallback = Arc<dyn Fn(&SearchProgress) + Send + Sync>;
+
+pub struct GraphSearch<G: GraphView = crate::graph::Graph> {
+  graph: Arc<G>,
   query: GraphSearchQuery,
-  front_distance_map: Vec<u8>,
-  back_distance_map: Vec<u8>,
-  front_queue: std::collections::VecDeque<usize>,
-  back_queue: std::collections::VecDeque<usize>,
+  workspace: SearchWorkspace,
+  /// Pages that must not be expanded.
+  filter: PageFilter,
+  avoid_not_found: Vec<String>,
+  via: Vec<usize>,
+  via_not_found: Vec<String>,
+  /// Requested start and end pages; empty when any of them was not found.
+  starts: Vec<usize>,
+  ends: Vec<usize>,
+  /// The start and end pages of the reported route, once found.
   start: Option<usize>,
   end: Option<usize>,
-  redirected_start: Option<usize>,
-  redirected_end: Option<usize>,
+  /// Prunes the search with landmark bounds when it is unfiltered.
+  oracle: Option<Arc<DistanceOracle>>,
+  /// Upper bound on the route length while pruning, `UNVISITED` when none
+  /// is known; `None` when not pruning.
+  alt_upper_bound: Option<u32>,
+  cancellation: Option<CancellationToken>,
+  deadline: Option<std::time::Instant>,
+  budget_exhausted: Option<Budget>,
+  /// Calls to `out_of_budget`, to read the clock only every so often.
+  budget_checks: u32,
+  /// Levels whose pages have at least this many links are expanded in
+  /// parallel; `PARALLEL_MIN_EDGES` unless changed for a test.
+  parallel_min_edges: usize,
+  progress: Option<ProgressCallback>,
   discovered_nodes: u32,
   visited_nodes: u32,
+  distance_overflow: bool,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Clone, Default)]
 pub struct GraphSearchQuery {
+  /// A title, or `|`-separated titles and `#<page id>`s to search from any
+  /// of them.
   pub start: String,
+  /// Like `start`; the route ends at whichever of them is closest.
   pub end: String,
   pub enable_date_related: Option<bool>,
   pub enable_list_article: Option<bool>,
+  /// Largest number of nodes to return; bigger DAGs are cut down to the
+  /// best nodes according to `rank_by`.
+  pub max_nodes: Option<usize>,
+  pub rank_by: Option<RankBy>,
+  /// `|`-separated pages that must not be on the path, as titles or as
+  /// `#<page id>`. The start and end are never avoided.
+  pub avoid: Option<String>,
+  /// `|`-separated pages, like `avoid`, that the path must pass through in
+  /// the given order, without passing any page twice.
+  pub via: Option<String>,
+  /// Which way links may be followed from start to end. A hop is free when
+  /// the page the link comes from is a redirect.
+  pub direction: Option<Direction>,
+  /// Stops after expanding this many pages.
+  pub max_visited: Option<u32>,
+  /// Longest route to look for.
+  pub max_depth: Option<u32>,
+  /// Stops after this many milliseconds.
+  pub timeout_ms: Option<u64>,
+}
+
+impl GraphSearchQuery {
+  /// The first of `via`, `direction` other than forward and `max_nodes`
+  /// that is set, for searches that only follow links forward and return
+  /// paths rather than a DAG.
+  pub fn unsupported_parameter(&self) -> Option<&'static str> {
+    if self.via.is_some() {
+      Some("via")
+    } else if self.direction.is_some_and(|direction| direction != Direction::Forward) {
+      Some("direction")
+    } else if self.max_nodes.is_some() {
+      Some("max_nodes")
+    } else {
+      None
+    }
+  }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Direction {
+  /// Along links, from a page to the pages it links to.
+  #[default]
+  #[serde(alias = "out")]
+  Forward,
+  /// Against links, from a page to the pages linking to it.
+  #[serde(alias = "in")]
+  Backward,
+  /// Either way.
+  Undirected,
+}
+
+/// The limit that stopped a search before it could finish.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Budget {
+  MaxVisited,
+  MaxDepth,
+  Timeout,
+  Cancelled,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum RankBy {
+  /// Nodes lying on the most shortest paths.
+  #[default]
+  Paths,
+  /// Intermediates with the most links.
+  HighDegree,
+  /// Intermediates with the fewest links.
+  LowDegree,
 }
 
 #[derive(Serialize, Deserialize)]
 pub struct GraphSearchResult {
   pub discovered_nodes: u32,
   pub visited_nodes: u32,
+  /// With several starts or ends, the pair that produced the shortest
+  /// route. Other starts and ends at the same distance can also be in the
+  /// DAG.
   pub start_node: Option<GraphSearchResultNode>,
   pub end_node: Option<GraphSearchResultNode>,
   pub nodes: Vec<GraphSearchResultNode>,
+  /// Edges in the order they are walked from start to end.
   pub edges: Vec<(u32, u32)>,
+  /// For each edge, whether it was walked along its link or against it.
+  pub edge_directions: Vec<Direction>,
   pub start_not_found: bool,
   pub end_not_found: bool,
   pub route_found: bool,
   pub is_start_end_some: bool,
+  /// Set when a path was longer than the largest representable distance;
+  /// nodes beyond that point are left out instead of reported wrongly.
+  pub distance_overflow: bool,
+  /// Set when the DAG had more than `max_nodes` nodes and was cut down.
+  pub truncated: bool,
+  pub dropped_nodes: usize,
+  pub dropped_paths: u64,
+  pub avoid_not_found: Vec<String>,
+  pub via_not_found: Vec<String>,
+  /// Set when the search stopped early; no route is reported then.
+  pub budget_exhausted: Option<Budget>,
+  /// Pages left to expand on the start and end side when the search ended.
+  pub front_frontier: usize,
+  pub back_frontier: usize,
   pub duration: std::time::Duration,
 }
 
-#[derive(Serialize, Deserialize)]
+/// How far a bidirectional search has got.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct SearchProgress {
+  /// Levels expanded so far, on both sides together.
+  pub level: u32,
+  pub front_frontier: usize,
+  pub back_frontier: usize,
+  pub discovered_nodes: u32,
+  pub visited_nodes: u32,
+  /// Pages where the two sides met, once they have; at most
+  /// `SearchProgress::MAX_MEETING_NODES` of them.
+  pub meeting_nodes: Vec<GraphSearchResultNode>,
+}
+
+impl SearchProgress {
+  pub const MAX_MEETING_NODES: usize = 100;
+}
+
+#[derive(Serialize, Deserialize, Clone)]
 pub struct GraphSearchResultNode {
   pub id: u32,
   pub ns: i32,
@@ -47,181 +198,450 @@ pub struct GraphSearchResultNode {
   pub is_redirect: bool,
   pub is_date_related: bool,
   pub is_list_article: bool,
-  pub distance: u8,
+  pub distance: u32,
 }
 
-impl From<(&crate::graph::Node, u8)> for GraphSearchResultNode {
-  fn from((node, distance): (&crate::graph::Node, u8)) -> Self {
+impl GraphSearchResultNode {
+  pub fn new<G: GraphView>(graph: &G, index: usize, distance: u32) -> Self {
     GraphSearchResultNode {
-      id: node.id,
-      ns: node.ns,
-      title: node.title.clone(),
-      is_redirect: node.is_redirect,
-      is_date_related: node.is_date_related,
-      is_list_article: node.is_list_article,
+      id: graph.id(index),
+      ns: graph.ns(index),
+      title: graph.title(index).to_string(),
+      is_redirect: graph.is_redirect(index),
+      is_date_related: graph.is_date_related(index),
+      is_list_article: graph.is_list_article(index),
       distance,
     }
   }
 }
 
-impl GraphSearch {
+impl<G: GraphView> GraphSearch<G> {
   pub fn new(
-    graph: Arc<crate::graph::Graph>,
+    graph: Arc<G>,
     title_to_index: Arc<std::collections::HashMap<String, usize>>,
+    id_to_index: Arc<std::collections::HashMap<u32, usize>>,
     query: GraphSearchQuery,
   ) -> Self {
-    let node_count = graph.nodes.len();
-    let front_distance_map = vec![u8::MAX; node_count];
-    let back_distance_map = vec![u8::MAX; node_count];
-    let front_queue = std::collections::VecDeque::new();
-    let back_queue = std::collections::VecDeque::new();
-
-    let start = title_to_index.get(&query.start).copied();
-    let end = title_to_index.get(&query.end).copied();
-
-    let redirected_start = if start.is_some() && graph.nodes[start.unwrap()].is_redirect {
-      let node = &graph.nodes[start.unwrap()];
-      Some(graph.forward_edges[node.forward_edge_range.0])
-    } else {
-      None
-    };
+    let workspace = SearchWorkspace::new(graph.node_count());
+    Self::with_workspace(graph, title_to_index, id_to_index, query, workspace)
+  }
 
-    let redirected_end = if end.is_some() && graph.nodes[end.unwrap()].is_redirect {
-      let node = &graph.nodes[end.unwrap()];
-      Some(graph.forward_edges[node.forward_edge_range.0])
-    } else {
-      None
-    };
+  /// Like `new`, but searches in a cleared workspace taken from a
+  /// `SearchWorkspacePool` instead of allocating one.
+  pub fn with_workspace(
+    graph: Arc<G>,
+    title_to_index: Arc<std::collections::HashMap<String, usize>>,
+    id_to_index: Arc<std::collections::HashMap<u32, usize>>,
+    query: GraphSearchQuery,
+    workspace: SearchWorkspace,
+  ) -> Self {
+    debug_assert_eq!(workspace.node_count(), graph.node_count());
+
+    let (starts, starts_not_found) = resolve_pages(&title_to_index, &id_to_index, Some(&query.start));
+    let starts = if starts_not_found.is_empty() { starts } else { Vec::new() };
+    let (ends, ends_not_found) = resolve_pages(&title_to_index, &id_to_index, Some(&query.end));
+    let ends = if ends_not_found.is_empty() { ends } else { Vec::new() };
+
+    let (filter, avoid_not_found) = PageFilter::for_query(graph.as_ref(), &title_to_index, &id_to_index, &query);
+    let (via, via_not_found) = resolve_pages(&title_to_index, &id_to_index, query.via.as_deref());
 
     GraphSearch {
       graph,
       query,
-      start,
-      end,
-      redirected_start: redirected_start,
-      redirected_end: redirected_end,
-      front_distance_map,
-      back_distance_map,
-      front_queue,
-      back_queue,
+      filter,
+      avoid_not_found,
+      via,
+      via_not_found,
+      starts,
+      ends,
+      start: None,
+      end: None,
+      workspace,
+      oracle: None,
+      alt_upper_bound: None,
+      cancellation: None,
+      deadline: None,
+      budget_exhausted: None,
+      budget_checks: 0,
+      parallel_min_edges: PARALLEL_MIN_EDGES,
+      progress: None,
       discovered_nodes: 0,
       visited_nodes: 0,
+      distance_overflow: false,
     }
   }
 
+  /// Uses `oracle` to skip pages that cannot be on a shortest route. Only
+  /// searches along links with every page enabled and nothing avoided are
+  /// pruned, since the oracle knows nothing about filters.
+  pub fn with_oracle(mut self, oracle: Option<Arc<DistanceOracle>>) -> Self {
+    self.oracle = oracle.filter(|oracle| oracle.matches(self.graph.as_ref()));
+    self
+  }
+
+  /// Stops the search once `cancellation` is cancelled.
+  pub fn with_cancellation(mut self, cancellation: CancellationToken) -> Self {
+    self.cancellation = Some(cancellation);
+    self
+  }
+
+  /// Reports progress to `progress` after every level, e.g. to stream it to
+  /// a client. With `via`, levels are counted per segment.
+  pub fn with_progress(mut self, progress: impl Fn(&SearchProgress) + Send + Sync + 'static) -> Self {
+    self.progress = Some(Arc::new(progress));
+    self
+  }
+
+  /// Expands levels in parallel from `edges` links on, e.g. `0` to
+  /// always or `usize::MAX` to never.
+  #[cfg(test)]
+  pub(crate) fn with_parallel_min_edges(mut self, edges: usize) -> Self {
+    self.parallel_min_edges = edges;
+    self
+  }
+
   pub fn exec(&mut self) -> GraphSearchResult {
     let start_time = std::time::Instant::now();
+    self.deadline = self.query.timeout_ms.map(|timeout| start_time + std::time::Duration::from_millis(timeout));
+
+    let (mut result, indices) = if self.via.is_empty() && self.via_not_found.is_empty() {
+      self.search()
+    } else {
+      self.search_via()
+    };
+    self.truncate(&mut result, &indices);
+    result.avoid_not_found = self.avoid_not_found.clone();
+    result.via_not_found = self.via_not_found.clone();
+    result.duration = start_time.elapsed();
+    result
+  }
+
+  /// Hands the workspace back, e.g. to return it to its pool.
+  pub fn into_workspace(self) -> SearchWorkspace {
+    self.workspace
+  }
+
+  /// The resolved start pages; empty when any of them was not found.
+  pub(crate) fn starts(&self) -> &[usize] {
+    &self.starts
+  }
 
-    if self.start.is_none() || self.end.is_none() {
-      return GraphSearchResult {
+  /// Searches segment by segment between the start, the via pages and the
+  /// end, and joins the segment DAGs with distances counted from the start.
+  ///
+  /// A segment avoids the pages of the segments before it and the stops
+  /// after it, so no page is on two segments and the joined DAG keeps one
+  /// node per page without cycles. Routes that would pass a page twice are
+  /// not found.
+  fn search_via(&mut self) -> (GraphSearchResult, Vec<usize>) {
+    if self.starts.is_empty() || self.ends.is_empty() {
+      return self.search();
+    }
+
+    let mut result = GraphSearchResult {
+      discovered_nodes: 0,
+      visited_nodes: 0,
+      start_node: Some(GraphSearchResultNode::new(self.graph.as_ref(), self.starts[0], 0)),
+      end_node: Some(GraphSearchResultNode::new(self.graph.as_ref(), self.ends[0], crate::graph::UNVISITED)),
+      nodes: Vec::new(),
+      edges: Vec::new(),
+      edge_directions: Vec::new(),
+      start_not_found: false,
+      end_not_found: false,
+      route_found: false,
+      is_start_end_some: false,
+      distance_overflow: false,
+      truncated: false,
+      dropped_nodes: 0,
+      dropped_paths: 0,
+      avoid_not_found: Vec::new(),
+      via_not_found: Vec::new(),
+      budget_exhausted: None,
+      front_frontier: 0,
+      back_frontier: 0,
+      duration: std::time::Duration::ZERO,
+    };
+    if !self.via_not_found.is_empty() {
+      return (result, Vec::new());
+    }
+
+    let mut stops = vec![self.starts.clone()];
+    stops.extend(self.via.iter().map(|&via| vec![via]));
+    stops.push(self.ends.clone());
+
+    let mut workspace = std::mem::replace(&mut self.workspace, SearchWorkspace::new(0));
+    let mut indices = Vec::new();
+    let mut node_ids = HashSet::new();
+    let mut edges = HashSet::new();
+    let mut used = HashSet::new();
+    let mut offset = 0u32;
+    let mut route_found = true;
+    for (i, stop) in stops.windows(2).enumerate() {
+      let own: HashSet<usize> = stop[0].iter().flat_map(|&index| [index, self.seed(index)]).collect();
+      let mut filter = self.filter.clone();
+      filter.avoided.extend(used.difference(&own));
+      filter.avoided.extend(stops[i + 2..].iter().flatten().flat_map(|&index| [index, self.seed(index)]));
+      let mut segment = GraphSearch {
+        graph: self.graph.clone(),
+        query: GraphSearchQuery {
+          max_nodes: None,
+          via: None,
+          max_visited: self.query.max_visited.map(|max| max.saturating_sub(result.visited_nodes)),
+          ..self.query.clone()
+        },
+        workspace,
+        filter,
+        avoid_not_found: Vec::new(),
+        via: Vec::new(),
+        via_not_found: Vec::new(),
+        starts: stop[0].clone(),
+        ends: stop[1].clone(),
+        start: None,
+        end: None,
+        oracle: self.oracle.clone(),
+        alt_upper_bound: None,
+        cancellation: self.cancellation.clone(),
+        deadline: self.deadline,
+        budget_exhausted: None,
+        budget_checks: 0,
+        parallel_min_edges: self.parallel_min_edges,
+        progress: self.progress.clone(),
+        discovered_nodes: 0,
+        visited_nodes: 0,
+        distance_overflow: false,
+      };
+      let (segment_result, segment_indices) = segment.search();
+      if self.start.is_none() {
+        self.start = segment.start;
+      }
+      self.end = segment.end;
+      workspace = segment.into_workspace();
+      workspace.clear();
+
+      result.discovered_nodes += segment_result.discovered_nodes;
+      result.visited_nodes += segment_result.visited_nodes;
+      result.distance_overflow |= segment_result.distance_overflow;
+      if segment_result.budget_exhausted.is_some() {
+        result.budget_exhausted = segment_result.budget_exhausted;
+        result.front_frontier = segment_result.front_frontier;
+        result.back_frontier = segment_result.back_frontier;
+        route_found = false;
+        break;
+      }
+      if !segment_result.route_found {
+        route_found = false;
+        break;
+      }
+
+      used.extend(segment_indices.iter().copied());
+      for (mut node, index) in segment_result.nodes.into_iter().zip(segment_indices) {
+        if node_ids.insert(node.id) {
+          node.distance = node.distance.saturating_add(offset);
+          result.nodes.push(node);
+          indices.push(index);
+        }
+      }
+      for (edge, direction) in segment_result.edges.into_iter().zip(segment_result.edge_directions) {
+        if edges.insert(edge) {
+          result.edges.push(edge);
+          result.edge_directions.push(direction);
+        }
+      }
+      let segment_distance = segment_result.end_node.map(|node| node.distance).unwrap_or(0);
+      offset = match offset.checked_add(segment_distance).filter(|&d| d != crate::graph::UNVISITED) {
+        Some(offset) => offset,
+        None => {
+          result.distance_overflow = true;
+          route_found = false;
+          break;
+        },
+      };
+    }
+    self.workspace = workspace;
+
+    if !route_found {
+      result.nodes.clear();
+      result.edges.clear();
+      result.edge_directions.clear();
+      return (result, Vec::new());
+    }
+    let (Some(start), Some(end)) = (self.start, self.end) else {
+      return (result, Vec::new());
+    };
+    result.route_found = true;
+    result.start_node = Some(GraphSearchResultNode::new(self.graph.as_ref(), start, 0));
+    result.end_node = Some(GraphSearchResultNode::new(self.graph.as_ref(), end, offset));
+    (result, indices)
+  }
+
+  fn search(&mut self) -> (GraphSearchResult, Vec<usize>) {
+    let start_time = std::time::Instant::now();
+
+    if self.starts.is_empty() || self.ends.is_empty() {
+      return (GraphSearchResult {
         discovered_nodes: self.discovered_nodes,
         visited_nodes: self.visited_nodes,
         start_node: None,
         end_node: None,
         nodes: Vec::new(),
         edges: Vec::new(),
-        start_not_found: self.start.is_none(),
-        end_not_found: self.end.is_none(),
+        edge_directions: Vec::new(),
+        start_not_found: self.starts.is_empty(),
+        end_not_found: self.ends.is_empty(),
         route_found: false,
         is_start_end_some: false,
+        distance_overflow: false,
+        truncated: false,
+        dropped_nodes: 0,
+        dropped_paths: 0,
+        avoid_not_found: Vec::new(),
+        via_not_found: Vec::new(),
+        budget_exhausted: None,
+        front_frontier: 0,
+        back_frontier: 0,
         duration: start_time.elapsed(),
-      };
+      }, Vec::new());
     }
 
-    let start_index = if self.redirected_start.is_some() {
-      self.redirected_start.unwrap()
-    } else {
-      self.start.unwrap()
-    };
-    self.front_queue.push_back(start_index);
-    self.front_distance_map[start_index] = 0;
-
-    let end_index = if self.redirected_end.is_some() {
-      self.redirected_end.unwrap()
-    } else {
-      self.end.unwrap()
-    };
-    self.back_queue.push_back(end_index);
-    self.back_distance_map[end_index] = 0;
+    self.seed_frontiers();
 
-    if start_index == end_index {
-      return GraphSearchResult {
+    let same = self.starts.iter().find_map(|&start| {
+      self.ends.iter().find(|&&end| self.seed(start) == self.seed(end)).map(|&end| (start, end))
+    });
+    if let Some((start, end)) = same {
+      self.start = Some(start);
+      self.end = Some(end);
+      return (GraphSearchResult {
         discovered_nodes: self.discovered_nodes,
         visited_nodes: self.visited_nodes,
         start_node: None,
         end_node: None,
         nodes: Vec::new(),
         edges: Vec::new(),
+        edge_directions: Vec::new(),
         start_not_found: false,
         end_not_found: false,
         route_found: true,
         is_start_end_some: true,
+        distance_overflow: false,
+        truncated: false,
+        dropped_nodes: 0,
+        dropped_paths: 0,
+        avoid_not_found: Vec::new(),
+        via_not_found: Vec::new(),
+        budget_exhausted: None,
+        front_frontier: 0,
+        back_frontier: 0,
         duration: start_time.elapsed(),
-      };
+      }, Vec::new());
     } 
 
+    self.alt_upper_bound = self.alt_upper_bound();
     let mut junction_nodes_index = Vec::new();
-    while self.front_queue.len() > 0 && self.back_queue.len() > 0 {
-      let discovered = if self.front_queue.len() < self.back_queue.len() {
-        self.front_mapping()
+    let mut level = 0;
+    while !self.workspace.front_queue.is_empty() && !self.workspace.back_queue.is_empty() {
+      if let Some(max_depth) = self.query.max_depth {
+        // Whatever the next level finds is longer than the levels so far.
+        let front_depth = self.workspace.front_distance_map.get(self.workspace.front_queue[0]);
+        let back_depth = self.workspace.back_distance_map.get(self.workspace.back_queue[0]);
+        if front_depth.saturating_add(back_depth) >= max_depth {
+          self.budget_exhausted = Some(Budget::MaxDepth);
+          break;
+        }
+      }
+
+      let discovered = if self.workspace.front_queue.len() < self.workspace.back_queue.len() {
+        self.mapping(true)
       } else {
-        self.back_mapping()
+        self.mapping(false)
       };
+      level += 1;
+      // A level cut short may miss shortest routes, so none are reported.
+      if self.budget_exhausted.is_some() {
+        break;
+      }
 
       for &node_index in &discovered {
-        if self.front_distance_map[node_index] != u8::MAX && self.back_distance_map[node_index] != u8::MAX {
+        if self.workspace.front_distance_map.is_visited(node_index) && self.workspace.back_distance_map.is_visited(node_index) {
           junction_nodes_index.push(node_index);
         }
       }
+      self.report_progress(level, &junction_nodes_index);
 
       if !junction_nodes_index.is_empty() {
         break;
       }
     }
 
-    let mut distance_map = self.merge_distance_map(junction_nodes_index);
+    // A level can meet the other side at pages on routes of different
+    // lengths; only the shortest are kept.
+    let length = |index: usize| {
+      u64::from(self.workspace.front_distance_map.get(index)) + u64::from(self.workspace.back_distance_map.get(index))
+    };
+    let shortest = junction_nodes_index.iter().map(|&index| length(index)).min();
+    junction_nodes_index.retain(|&index| Some(length(index)) == shortest);
+    self.merge_distance_map(junction_nodes_index);
 
-    if self.redirected_start.is_some() {
-      distance_map[self.start.unwrap()] = distance_map[self.redirected_start.unwrap()];
+    for i in 0..self.starts.len() + self.ends.len() {
+      let index = if i < self.starts.len() { self.starts[i] } else { self.ends[i - self.starts.len()] };
+      let seed = self.seed(index);
+      if seed != index && self.workspace.distance_map.is_visited(seed) {
+        self.workspace.distance_map.set(index, self.workspace.distance_map.get(seed));
+      }
     }
 
-    if self.redirected_end.is_some() {
-      distance_map[self.end.unwrap()] = distance_map[self.redirected_end.unwrap()];
-    }
+    let distance_map = &self.workspace.distance_map;
+    self.start = self.starts.iter().copied().find(|&start| distance_map.is_visited(start));
+    self.end = self.start.and_then(|start| {
+      self.ends
+        .iter()
+        .copied()
+        .find(|&end| distance_map.is_visited(end) && self.reaches(self.seed(start), self.seed(end)))
+    });
+    let route_found = self.end.is_some();
 
-    let (nodes, edges) = if distance_map[self.end.unwrap()] == u8::MAX {
-      (Vec::new(), Vec::new())
+    let (nodes, edges, edge_directions, indices) = if !route_found {
+      (Vec::new(), Vec::new(), Vec::new(), Vec::new())
     } else {
-      self.shortest_path_graph(&distance_map)
+      self.shortest_path_graph()
     };
 
-    let start_node = GraphSearchResultNode::from((
-      &self.graph.nodes[self.start.unwrap()],
-      distance_map[self.start.unwrap()],
-    ));
-
-    let end_node = GraphSearchResultNode::from((
-      &self.graph.nodes[self.end.unwrap()],
-      distance_map[self.end.unwrap()],
-    )); 
+    let start = self.start.unwrap_or(self.starts[0]);
+    let end = self.end.unwrap_or(self.ends[0]);
+    let start_node = GraphSearchResultNode::new(self.graph.as_ref(), start, self.workspace.distance_map.get(start));
+    let end_node = GraphSearchResultNode::new(self.graph.as_ref(), end, self.workspace.distance_map.get(end));
 
-    GraphSearchResult {
+    let mut result = GraphSearchResult {
       discovered_nodes: self.discovered_nodes,
       visited_nodes: self.visited_nodes,
       duration: start_time.elapsed(),
-      nodes: nodes,
-      edges: edges,
-      start_not_found: self.start.is_none(),
-      end_not_found: self.end.is_none(),
+      nodes,
+      edges,
+      edge_directions,
+      start_not_found: false,
+      end_not_found: false,
       is_start_end_some: false,
-      route_found: distance_map[self.end.unwrap()] != u8::MAX,
+      route_found,
+      distance_overflow: self.distance_overflow,
       start_node: Some(start_node),
       end_node: Some(end_node),
-    }
+      truncated: false,
+      dropped_nodes: 0,
+      dropped_paths: 0,
+      avoid_not_found: Vec::new(),
+      via_not_found: Vec::new(),
+      budget_exhausted: self.budget_exhausted,
+      front_frontier: self.workspace.front_queue.len(),
+      back_frontier: self.workspace.back_queue.len(),
+    };
+
+    result.duration = start_time.elapsed();
+    (result, indices)
   }
 
-  fn merge_distance_map(&self, junction_nodes_index: Vec::<usize>) -> Vec<u8> {
-    let mut distance_map = vec![u8::MAX; self.graph.nodes.len()];
+  fn merge_distance_map(&mut self, junction_nodes_index: Vec::<usize>) {
+    let distance_map = &mut self.workspace.distance_map;
+    let direction = self.query.direction.unwrap_or_default();
 
     let mut queue = std::collections::VecDeque::new();
     for &node_index in &junction_nodes_index {
@@ -229,21 +649,18 @@ impl GraphSearch {
     }
     
     while let Some(node_index) = queue.pop_front() {
-      let distance = self.front_distance_map[node_index];
-      distance_map[node_index] = distance;
+      let distance = self.workspace.front_distance_map.get(node_index);
+      distance_map.set(node_index, distance);
 
-      let node = &self.graph.nodes[node_index];
-      let (s, e) = node.backward_edge_range;
-      for &neighbor_index in &self.graph.backward_edges[s..e] {
-        if distance_map[neighbor_index] != u8::MAX {
+      for (neighbor_index, free) in in_edges(self.graph.as_ref(), direction, node_index) {
+        if distance_map.is_visited(neighbor_index) {
           continue;
         }
 
-        let neighbor = &self.graph.nodes[neighbor_index];
-        let neighbor_distance = self.front_distance_map[neighbor_index];
-        if neighbor.is_redirect && neighbor_distance == distance {
+        let neighbor_distance = self.workspace.front_distance_map.get(neighbor_index);
+        if free && neighbor_distance == distance {
           queue.push_front(neighbor_index);
-        } else if !neighbor.is_redirect && neighbor_distance < distance {
+        } else if !free && neighbor_distance < distance {
           queue.push_back(neighbor_index);
         }
       }
@@ -255,183 +672,975 @@ impl GraphSearch {
     }
 
     while let Some(node_index) = queue.pop_front() {
-      let node = &self.graph.nodes[node_index];
-      let distance = distance_map[node_index];
-      let back_distance = self.back_distance_map[node_index];
-      let (s, e) = node.forward_edge_range;
-      for &neighbor_index in &self.graph.forward_edges[s..e] {
-        if distance_map[neighbor_index] != u8::MAX {
+      let distance = distance_map.get(node_index);
+      let back_distance = self.workspace.back_distance_map.get(node_index);
+      for (neighbor_index, free) in out_edges(self.graph.as_ref(), direction, node_index) {
+        if distance_map.is_visited(neighbor_index) {
           continue;
         }
 
-        if self.back_distance_map[neighbor_index] == u8::MAX {
+        if !self.workspace.back_distance_map.is_visited(neighbor_index) {
           continue;
         }
 
-        let neighbor_distance = self.back_distance_map[neighbor_index];
+        let neighbor_distance = self.workspace.back_distance_map.get(neighbor_index);
 
-        if node.is_redirect && neighbor_distance == back_distance {
-          distance_map[neighbor_index] = distance;
+        if free && neighbor_distance == back_distance {
+          distance_map.set(neighbor_index, distance);
           queue.push_front(neighbor_index);
-        } else if !node.is_redirect && neighbor_distance < back_distance {
-          distance_map[neighbor_index] = distance + 1;
+        } else if !free && neighbor_distance < back_distance {
+          let Some(next) = next_distance(distance) else {
+            self.distance_overflow = true;
+            continue;
+          };
+          distance_map.set(neighbor_index, next);
           queue.push_back(neighbor_index);
         }
       }
     }
 
-    return distance_map;
   }
 
-  fn front_mapping(&mut self) -> Vec<usize> {
-    if self.front_queue.is_empty() {
+  /// Expands the pages at the front of the start side queue (or the end
+  /// side when not `forward`) by one level and returns the pages it
+  /// reached, for finding where the two sides meet.
+  ///
+  /// Pages reached for free share the level's distance, so the level is
+  /// first closed under free links and only then are the other links
+  /// followed. A page a redirect leads to is thus never given the next
+  /// distance because another link to it happened to be seen first.
+  fn mapping(&mut self, forward: bool) -> Vec<usize> {
+    let queue = if forward { &self.workspace.front_queue } else { &self.workspace.back_queue };
+    if queue.is_empty() {
       return Vec::new();
     }
+    if self.is_large_level(forward) {
+      return self.parallel_mapping(forward);
+    }
+    let graph = self.graph.clone();
+    let direction = self.query.direction.unwrap_or_default();
+    let (mut level, current_distance) = self.take_level(forward);
+
     let mut discovered = Vec::new();
-    let current_distance = self.front_distance_map[self.front_queue[0]];
+    let mut i = 0;
+    while i < level.len() {
+      let node_index = level[i];
+      i += 1;
+      for (neighbor_index, free) in level_edges(graph.as_ref(), direction, forward, node_index) {
+        if !free || self.side_distance_map(forward).is_visited(neighbor_index) {
+          continue;
+        }
+        self.discovered_nodes += 1;
+        if !self.is_enabled(neighbor_index) || self.alt_prunes(neighbor_index, current_distance, forward) {
+          continue;
+        }
+        let distance_map = if forward { &mut self.workspace.front_distance_map } else { &mut self.workspace.back_distance_map };
+        distance_map.set(neighbor_index, current_distance);
+        level.push(neighbor_index);
+        discovered.push(neighbor_index);
+      }
+    }
 
-    while !self.front_queue.is_empty() {
-      let node_index = self.front_queue[0];
-      if self.front_distance_map[node_index] > current_distance {
+    for (position, &node_index) in level.iter().enumerate() {
+      if self.out_of_budget() {
+        self.requeue(forward, &level[position..]);
         break;
       }
       self.visited_nodes += 1;
-      self.front_queue.pop_front();
-      let node = &self.graph.nodes[node_index];
-      let (s, e) = node.forward_edge_range;
-      for &neighbor_index in &self.graph.forward_edges[s..e] {
-        if self.front_distance_map[neighbor_index] != u8::MAX {
+      for (neighbor_index, free) in level_edges(graph.as_ref(), direction, forward, node_index) {
+        if free || self.side_distance_map(forward).is_visited(neighbor_index) {
           continue;
         }
         self.discovered_nodes += 1;
-
-        let neighbor = &self.graph.nodes[neighbor_index];
-        if neighbor.is_date_related  && !self.query.enable_date_related.unwrap_or(false) {
+        if !self.is_enabled(neighbor_index) {
           continue;
         }
-        if neighbor.is_list_article && !self.query.enable_list_article.unwrap_or(false) {
+        let Some(next) = next_distance(current_distance) else {
+          self.distance_overflow = true;
+          continue;
+        };
+        if self.alt_prunes(neighbor_index, next, forward) {
           continue;
         }
+        let (queue, distance_map) = if forward {
+          (&mut self.workspace.front_queue, &mut self.workspace.front_distance_map)
+        } else {
+          (&mut self.workspace.back_queue, &mut self.workspace.back_distance_map)
+        };
+        distance_map.set(neighbor_index, next);
+        queue.push_back(neighbor_index);
+        discovered.push(neighbor_index);
+      }
+    }
+    discovered
+  }
 
+  fn side_distance_map(&self, forward: bool) -> &DistanceMap {
+    if forward { &self.workspace.front_distance_map } else { &self.workspace.back_distance_map }
+  }
 
-        if node.is_redirect {
-          self.front_distance_map[neighbor_index] = current_distance;
-          self.front_queue.push_front(neighbor_index);
-        } else {
-          self.front_distance_map[neighbor_index] = current_distance + 1;
-          self.front_queue.push_back(neighbor_index);
-          discovered.push(neighbor_index);
-        }
+  /// Takes the pages at the current distance off the front of the queue,
+  /// with that distance.
+  fn take_level(&mut self, forward: bool) -> (Vec<usize>, u32) {
+    let (queue, distance_map) = if forward {
+      (&mut self.workspace.front_queue, &self.workspace.front_distance_map)
+    } else {
+      (&mut self.workspace.back_queue, &self.workspace.back_distance_map)
+    };
+    let current_distance = distance_map.get(queue[0]);
+    let mut level = Vec::new();
+    while let Some(&node_index) = queue.front() {
+      if distance_map.get(node_index) > current_distance {
+        break;
       }
+      level.push(node_index);
+      queue.pop_front();
     }
-    return discovered;
+    (level, current_distance)
   }
 
-  fn back_mapping(&mut self) -> Vec<usize> {
-    if self.back_queue.is_empty() {
-      return Vec::new();
+  /// Puts pages of a level cut short back at the front of the queue, in
+  /// their order.
+  fn requeue(&mut self, forward: bool, pages: &[usize]) {
+    let queue = if forward { &mut self.workspace.front_queue } else { &mut self.workspace.back_queue };
+    for &node_index in pages.iter().rev() {
+      queue.push_front(node_index);
+    }
+  }
+
+  fn report_progress(&self, level: u32, junction_nodes_index: &[usize]) {
+    let Some(progress) = &self.progress else {
+      return;
+    };
+    progress(&SearchProgress {
+      level,
+      front_frontier: self.workspace.front_queue.len(),
+      back_frontier: self.workspace.back_queue.len(),
+      discovered_nodes: self.discovered_nodes,
+      visited_nodes: self.visited_nodes,
+      meeting_nodes: junction_nodes_index
+        .iter()
+        .take(SearchProgress::MAX_MEETING_NODES)
+        .map(|&index| {
+          let distance = self.workspace.front_distance_map.get(index);
+          GraphSearchResultNode::new(self.graph.as_ref(), index, distance)
+        })
+        .collect(),
+    });
+  }
+
+  /// Whether the pages at the front of the start side queue (or the end
+  /// side when not `forward`) have enough links to expand in parallel.
+  fn is_large_level(&self, forward: bool) -> bool {
+    let direction = self.query.direction.unwrap_or_default();
+    let (queue, distance_map) = if forward {
+      (&self.workspace.front_queue, &self.workspace.front_distance_map)
+    } else {
+      (&self.workspace.back_queue, &self.workspace.back_distance_map)
+    };
+    let current_distance = distance_map.get(queue[0]);
+    let mut edges = 0;
+    for &node_index in queue.iter().take_while(|&&index| distance_map.get(index) == current_distance) {
+      edges += if forward {
+        out_degree(self.graph.as_ref(), direction, node_index)
+      } else {
+        in_degree(self.graph.as_ref(), direction, node_index)
+      };
+      if edges >= self.parallel_min_edges {
+        return true;
+      }
     }
+    false
+  }
+
+  /// Expands one level like `mapping` with the work spread over threads.
+  /// Rounds over the free links close the level first, then one round
+  /// follows the other links out of the whole level. A round goes either
+  /// top-down from its frontier or, when the frontier is large, bottom-up
+  /// by looking for a parent in the level from every unvisited page.
+  fn parallel_mapping(&mut self, forward: bool) -> Vec<usize> {
+    let (mut level, current_distance) = self.take_level(forward);
+
     let mut discovered = Vec::new();
-    let current_distance = self.back_distance_map[self.back_queue[0]];
+    let mut frontier = level.clone();
+    while !frontier.is_empty() {
+      // A round is a lot of work, so the clock is read before every one.
+      if self.check_budget(true) {
+        self.requeue(forward, &level);
+        return Vec::new();
+      }
+      let (discovered_nodes, reached) = self.parallel_round(forward, &frontier, current_distance, true);
+      self.discovered_nodes += discovered_nodes as u32;
+      let distance_map = if forward { &mut self.workspace.front_distance_map } else { &mut self.workspace.back_distance_map };
+      for &node_index in &reached {
+        self.workspace.claimed.release(node_index);
+        distance_map.set(node_index, current_distance);
+      }
+      level.extend_from_slice(&reached);
+      discovered.extend_from_slice(&reached);
+      frontier = reached;
+    }
 
-    while !self.back_queue.is_empty() {
-      let node_index = self.back_queue[0];
+    if self.check_budget(true) {
+      self.requeue(forward, &level);
+      return Vec::new();
+    }
+    self.visited_nodes += level.len() as u32;
+    let (discovered_nodes, reached) = self.parallel_round(forward, &level, current_distance, false);
+    self.discovered_nodes += discovered_nodes as u32;
+    for node_index in reached {
+      self.workspace.claimed.release(node_index);
+      let Some(next) = next_distance(current_distance) else {
+        self.distance_overflow = true;
+        continue;
+      };
+      let (queue, distance_map) = if forward {
+        (&mut self.workspace.front_queue, &mut self.workspace.front_distance_map)
+      } else {
+        (&mut self.workspace.back_queue, &mut self.workspace.back_distance_map)
+      };
+      distance_map.set(node_index, next);
+      queue.push_back(node_index);
+      discovered.push(node_index);
+    }
+    discovered
+  }
 
-      if self.back_distance_map[node_index] > current_distance {
-        break;
+  /// The pages one round of `parallel_mapping` reaches over free links (or
+  /// the other links when not `free`), each once, and how many unvisited
+  /// pages it saw.
+  fn parallel_round(&self, forward: bool, frontier: &[usize], current_distance: u32, free: bool) -> (usize, Vec<usize>) {
+    let graph = self.graph.as_ref();
+    let direction = self.query.direction.unwrap_or_default();
+    let distance_map = self.side_distance_map(forward);
+    let bottom_up = frontier.len() * BOTTOM_UP_ALPHA > graph.node_count() - distance_map.visited().len();
+    let distance = if free { Some(current_distance) } else { next_distance(current_distance) };
+
+    let reach = |(mut seen, mut reached): (usize, Vec<usize>), node_index: usize| {
+      seen += 1;
+      let enabled = self.is_enabled(node_index)
+        && !distance.is_some_and(|distance| self.alt_prunes(node_index, distance, forward));
+      // Top-down, several frontier pages can reach the same page.
+      if enabled && (bottom_up || self.workspace.claimed.claim(node_index)) {
+        reached.push(node_index);
       }
-      self.visited_nodes += 1;
+      (seen, reached)
+    };
+    let merge = |(seen, mut reached): (usize, Vec<usize>), (other_seen, other_reached): (usize, Vec<usize>)| {
+      reached.extend(other_reached);
+      (seen + other_seen, reached)
+    };
 
-      self.back_queue.pop_front();
-      let node = &self.graph.nodes[node_index];
-      let (s, e) = node.backward_edge_range;
-      for &neighbor_index in &self.graph.backward_edges[s..e] {
-        if self.back_distance_map[neighbor_index] != u8::MAX {
-          continue;
-        }
-        self.discovered_nodes += 1;
+    if bottom_up {
+      (0..graph.node_count())
+        .into_par_iter()
+        .filter(|&node_index| !distance_map.is_visited(node_index))
+        .filter(|&node_index| {
+          level_edges(graph, direction, !forward, node_index)
+            .any(|(parent, edge_free)| edge_free == free && distance_map.get(parent) == current_distance)
+        })
+        .fold(Default::default, reach)
+        .reduce(Default::default, merge)
+    } else {
+      frontier
+        .par_iter()
+        .flat_map_iter(|&node_index| level_edges(graph, direction, forward, node_index))
+        .filter(|&(neighbor_index, edge_free)| edge_free == free && !distance_map.is_visited(neighbor_index))
+        .map(|(neighbor_index, _)| neighbor_index)
+        .fold(Default::default, reach)
+        .reduce(Default::default, merge)
+    }
+  }
 
-        let neighbor = &self.graph.nodes[neighbor_index];
-        if neighbor.is_date_related  && !self.query.enable_date_related.unwrap_or(false) {  
-          continue;
-        }
-        if neighbor.is_list_article && !self.query.enable_list_article.unwrap_or(false) {
+  /// Searches from `starts`, given as node indices, instead of the start
+  /// pages of the query.
+  pub(crate) fn with_starts(mut self, starts: Vec<usize>) -> Self {
+    self.starts = starts;
+    self
+  }
+
+  /// The limit that stopped the search, if one did.
+  pub(crate) fn budget_exhausted(&self) -> Option<Budget> {
+    self.budget_exhausted
+  }
+
+  /// Maps everything reachable from the start pages along links, and
+  /// everything reaching the end pages, with the same redirect-aware
+  /// expansion as the bidirectional search. Returns the forward and backward
+  /// distance maps and whether a distance overflowed.
+  pub(crate) fn map_reachable(&mut self) -> (&DistanceMap, &DistanceMap, bool) {
+    self.seed_frontiers();
+    while !self.workspace.front_queue.is_empty() && !self.out_of_budget() {
+      self.mapping(true);
+    }
+    while !self.workspace.back_queue.is_empty() && !self.out_of_budget() {
+      self.mapping(false);
+    }
+    (&self.workspace.front_distance_map, &self.workspace.back_distance_map, self.distance_overflow)
+  }
+
+  /// Every start and end seeds its frontier, through its redirect target
+  /// when it is a redirect.
+  fn seed_frontiers(&mut self) {
+    for i in 0..self.starts.len() {
+      let seed = self.seed(self.starts[i]);
+      if !self.workspace.front_distance_map.is_visited(seed) {
+        self.workspace.front_distance_map.set(seed, 0);
+        self.workspace.front_queue.push_back(seed);
+      }
+    }
+    for i in 0..self.ends.len() {
+      let seed = self.seed(self.ends[i]);
+      if !self.workspace.back_distance_map.is_visited(seed) {
+        self.workspace.back_distance_map.set(seed, 0);
+        self.workspace.back_queue.push_back(seed);
+      }
+    }
+  }
+
+  /// Where the search for a requested page starts: its redirect target, or
+  /// the page itself.
+  fn seed(&self, index: usize) -> usize {
+    resolve_redirect(self.graph.as_ref(), index)
+  }
+
+  /// Whether `to` is reachable from `from` along the merged shortest routes.
+  fn reaches(&self, from: usize, to: usize) -> bool {
+    let distance_map = &self.workspace.distance_map;
+    let mut seen = HashSet::from([from]);
+    let mut stack = vec![from];
+    while let Some(node_index) = stack.pop() {
+      if node_index == to {
+        return true;
+      }
+      let distance = distance_map.get(node_index);
+      for (neighbor_index, free) in out_edges(self.graph.as_ref(), self.query.direction.unwrap_or_default(), node_index) {
+        if !distance_map.is_visited(neighbor_index) {
           continue;
         }
-
-        if neighbor.is_redirect {
-          self.back_distance_map[neighbor_index] = current_distance;
-          self.back_queue.push_front(neighbor_index);
+        let neighbor_distance = distance_map.get(neighbor_index);
+        let on_route = if free {
+          neighbor_distance == distance
         } else {
-          self.back_distance_map[neighbor_index] = current_distance + 1;
-          self.back_queue.push_back(neighbor_index);
-          discovered.push(neighbor_index);
+          next_distance(distance) == Some(neighbor_distance)
+        };
+        if on_route && seen.insert(neighbor_index) {
+          stack.push(neighbor_index);
         }
       }
     }
-    return discovered;
+    false
+  }
+
+  /// Whether `max_visited`, the deadline or cancellation stops the search,
+  /// remembering which one did. The clock is only read every so many calls.
+  fn out_of_budget(&mut self) -> bool {
+    self.budget_checks = self.budget_checks.wrapping_add(1);
+    self.check_budget(self.budget_checks % 256 == 0)
+  }
+
+  /// Like `out_of_budget`, checking the deadline only when `read_clock`.
+  fn check_budget(&mut self, read_clock: bool) -> bool {
+    if self.budget_exhausted.is_some() {
+      return true;
+    }
+    let budget = if self.query.max_visited.is_some_and(|max| self.visited_nodes >= max) {
+      Budget::MaxVisited
+    } else if self.cancellation.as_ref().is_some_and(|cancellation| cancellation.is_cancelled()) {
+      Budget::Cancelled
+    } else if self.deadline.is_some_and(|deadline| read_clock && std::time::Instant::now() >= deadline) {
+      Budget::Timeout
+    } else {
+      return false;
+    };
+    self.budget_exhausted = Some(budget);
+    true
+  }
+
+  /// The oracle's best upper bound over all start and end pairs, when the
+  /// search can be pruned at all.
+  fn alt_upper_bound(&self) -> Option<u32> {
+    let oracle = self.oracle.as_ref()?;
+    let unfiltered = self.query.direction.unwrap_or_default() == Direction::Forward
+      && self.filter.is_empty();
+    if !unfiltered {
+      return None;
+    }
+    let upper_bound = self.starts
+      .iter()
+      .flat_map(|&start| self.ends.iter().map(move |&end| (start, end)))
+      .filter_map(|(start, end)| oracle.upper_bound(self.seed(start), self.seed(end)))
+      .min();
+    Some(upper_bound.unwrap_or(crate::graph::UNVISITED))
+  }
+
+  /// Whether a page found at `distance` from the start side (or from the end
+  /// side when not `forward`) cannot be on a shortest route.
+  fn alt_prunes(&self, index: usize, distance: u32, forward: bool) -> bool {
+    let (Some(oracle), Some(upper_bound)) = (&self.oracle, self.alt_upper_bound) else {
+      return false;
+    };
+    let lower_bound = if forward {
+      self.ends.iter().filter_map(|&end| oracle.lower_bound(index, self.seed(end))).min()
+    } else {
+      self.starts.iter().filter_map(|&start| oracle.lower_bound(self.seed(start), index)).min()
+    };
+    match lower_bound {
+      Some(lower_bound) => distance.saturating_add(lower_bound) > upper_bound,
+      None => true,
+    }
   }
 
-  fn shortest_path_graph(&self, distance_map: &Vec<u8>) -> (Vec<GraphSearchResultNode>, Vec<(u32, u32)>) { 
+  fn is_enabled(&self, index: usize) -> bool {
+    self.filter.allows(self.graph.as_ref(), index)
+  }
+
+  /// Cuts the DAG in `result` down to `max_nodes` nodes. `indices` are the
+  /// graph indices of `result.nodes`.
+  fn truncate(&self, result: &mut GraphSearchResult, indices: &[usize]) {
+    let Some(max_nodes) = self.query.max_nodes else {
+      return;
+    };
+    if result.nodes.len() <= max_nodes {
+      return;
+    }
+    let Some(dag) = ShortestPathDag::new(result) else {
+      return;
+    };
+
+    let degree = |i: usize| {
+      (self.graph.forward_degree(indices[i]) + self.graph.backward_degree(indices[i])) as u64
+    };
+    let path_count = dag.path_count();
+    let keep = match self.query.rank_by.unwrap_or_default() {
+      RankBy::Paths => dag.select(max_nodes, |i| dag.paths_through(i)),
+      RankBy::HighDegree => dag.select(max_nodes, degree),
+      RankBy::LowDegree => dag.select(max_nodes, |i| u64::MAX - degree(i)),
+    };
+
+    let kept_ids: std::collections::HashSet<u32> = result.nodes
+      .iter()
+      .zip(&keep)
+      .filter(|(_, &keep)| keep)
+      .map(|(node, _)| node.id)
+      .collect();
+    let node_count = result.nodes.len();
+    result.nodes.retain(|node| kept_ids.contains(&node.id));
+    let (edges, edge_directions) = std::mem::take(&mut result.edges)
+      .into_iter()
+      .zip(std::mem::take(&mut result.edge_directions))
+      .filter(|((s, t), _)| kept_ids.contains(s) && kept_ids.contains(t))
+      .unzip();
+    result.edges = edges;
+    result.edge_directions = edge_directions;
+
+    let remaining_paths = ShortestPathDag::new(result).map(|dag| dag.path_count()).unwrap_or(0);
+    result.truncated = true;
+    result.dropped_nodes = node_count - result.nodes.len();
+    result.dropped_paths = path_count.saturating_sub(remaining_paths);
+  }
 
+  /// The nodes and edges on shortest routes, with the edge directions and
+  /// the graph index of each node.
+  fn shortest_path_graph(&mut self) -> ShortestPathGraph {
+    let distance_map = &self.workspace.distance_map;
+    let visited = &mut self.workspace.visited;
+    let direction = self.query.direction.unwrap_or_default();
     let mut queue = std::collections::VecDeque::new();
-    let mut visited = vec![false; self.graph.nodes.len()];
     let mut nodes = Vec::new();
-    let mut edges = Vec::new();
-    
-    if self.redirected_start.is_some() {
-      queue.push_back(self.redirected_start.unwrap());
-    } else {
-      queue.push_back(self.start.unwrap());
+    let mut links = Vec::new();
+    let mut edge_directions = Vec::new();
+    let mut indices = Vec::new();
+    // Free links taken so far. Walking both ways, a redirect and its target
+    // are each free to reach from the other, so a free link leading back to
+    // where it comes from is left out to keep the DAG free of cycles.
+    let mut free_links: HashMap<usize, Vec<usize>> = HashMap::new();
+
+    for &start in &self.starts {
+      // A redirect start comes before its target, so that a route leaving it
+      // against a link, other than through its target, is kept.
+      let seed = resolve_redirect(self.graph.as_ref(), start);
+      if seed != start && distance_map.is_visited(start) {
+        queue.push_back(start);
+      }
+      if distance_map.is_visited(seed) {
+        queue.push_back(seed);
+      }
     }
 
     while let Some(node_index) = queue.pop_front() {
-      if visited[node_index] {
+      if visited.is_visited(node_index) {
         continue;
       }
-      visited[node_index] = true;
-      let distance = distance_map[node_index];
-      nodes.push(GraphSearchResultNode::from((&self.graph.nodes[node_index], distance)));
+      visited.set(node_index, 0);
+      let distance = distance_map.get(node_index);
+      nodes.push(GraphSearchResultNode::new(self.graph.as_ref(), node_index, distance));
+      indices.push(node_index);
 
-      let node = &self.graph.nodes[node_index];
-      let (s, e) = node.forward_edge_range;
-      for &neighbor_index in &self.graph.forward_edges[s..e] {
-        if distance_map[neighbor_index] == u8::MAX {
+      // `out_edges` yields the forward links first.
+      let forward_degree = if direction == Direction::Backward { 0 } else { self.graph.forward_degree(node_index) };
+      let mut linked = HashSet::new();
+      for (i, (neighbor_index, free)) in out_edges(self.graph.as_ref(), direction, node_index).enumerate() {
+        // Routes begin at the starts; a redirect start only hangs off its
+        // target, even when walking against links would reach it for free.
+        if !distance_map.is_visited(neighbor_index) || self.starts.contains(&neighbor_index) {
           continue;
         }
-        let neighbor_distance = distance_map[neighbor_index];
-        let neighbor = &self.graph.nodes[neighbor_index];
+        let neighbor_distance = distance_map.get(neighbor_index);
 
-        if node.is_redirect && distance == neighbor_distance {
+        let on_route = if free {
+          distance == neighbor_distance && !free_path(&free_links, neighbor_index, node_index)
+        } else {
+          next_distance(distance) == Some(neighbor_distance)
+        };
+        if !on_route || !linked.insert(neighbor_index) {
+          continue;
+        }
+        if free {
+          free_links.entry(node_index).or_default().push(neighbor_index);
           queue.push_front(neighbor_index);
-          edges.push((node.id, neighbor.id));
-        } else if !node.is_redirect && distance + 1 == neighbor_distance {
+        } else {
           queue.push_back(neighbor_index);
-          edges.push((node.id, neighbor.id));
         }
+        links.push((node_index, neighbor_index));
+        edge_directions.push(if i < forward_degree { Direction::Forward } else { Direction::Backward });
+      }
+    }
+
+    // Requested redirects hang off the DAG with an edge to their target.
+    for &index in self.starts.iter().chain(&self.ends) {
+      let Some(target) = self.graph.redirect_target(index) else {
+        continue;
+      };
+      if distance_map.is_visited(index) && !visited.is_visited(index) {
+        visited.set(index, 0);
+        let distance = distance_map.get(target);
+        nodes.push(GraphSearchResultNode::new(self.graph.as_ref(), index, distance));
+        indices.push(index);
+        links.push((index, target));
+        edge_directions.push(Direction::Forward);
+      }
+    }
+
+    // A page reached for free from where a route ends, and only leading
+    // back there, is on no route once that loop is left out.
+    let mut on_route: HashSet<usize> = self.ends
+      .iter()
+      .flat_map(|&end| [end, resolve_redirect(self.graph.as_ref(), end)])
+      .filter(|&index| visited.is_visited(index))
+      .collect();
+    let mut parents: HashMap<usize, Vec<usize>> = HashMap::new();
+    for &(source, target) in &links {
+      parents.entry(target).or_default().push(source);
+    }
+    let mut stack: Vec<usize> = on_route.iter().copied().collect();
+    while let Some(index) = stack.pop() {
+      for &parent in parents.get(&index).into_iter().flatten() {
+        if on_route.insert(parent) {
+          stack.push(parent);
+        }
+      }
+    }
+    if on_route.len() < indices.len() {
+      (nodes, indices) = nodes.into_iter().zip(indices).filter(|(_, index)| on_route.contains(index)).unzip();
+      (links, edge_directions) = links
+        .into_iter()
+        .zip(edge_directions)
+        .filter(|((source, target), _)| on_route.contains(source) && on_route.contains(target))
+        .unzip();
+    }
+
+    let edges = links
+      .iter()
+      .map(|&(source, target)| (self.graph.id(source), self.graph.id(target)))
+      .collect();
+    (nodes, edges, edge_directions, indices)
+  }
+}
+
+type ShortestPathGraph = (Vec<GraphSearchResultNode>, Vec<(u32, u32)>, Vec<Direction>, Vec<usize>);
+
+/// Edges leaving a node when walking in `direction`, each with whether the
+/// hop is free because the link comes from a redirect.
+fn out_edges<G: GraphView>(graph: &G, direction: Direction, index: usize) -> impl Iterator<Item = (usize, bool)> + '_ {
+  let forward = (direction != Direction::Backward).then(|| {
+    let free = graph.is_redirect(index);
+    graph.forward_neighbors(index).map(move |neighbor| (neighbor, free))
+  });
+  let backward = (direction != Direction::Forward).then(|| {
+    graph.backward_neighbors(index).map(|neighbor| (neighbor, graph.is_redirect(neighbor)))
+  });
+  forward.into_iter().flatten().chain(backward.into_iter().flatten())
+}
+
+fn out_degree<G: GraphView>(graph: &G, direction: Direction, index: usize) -> usize {
+  match direction {
+    Direction::Forward => graph.forward_degree(index),
+    Direction::Backward => graph.backward_degree(index),
+    Direction::Undirected => graph.forward_degree(index) + graph.backward_degree(index),
+  }
+}
+
+fn in_degree<G: GraphView>(graph: &G, direction: Direction, index: usize) -> usize {
+  match direction {
+    Direction::Forward => graph.backward_degree(index),
+    Direction::Backward => graph.forward_degree(index),
+    Direction::Undirected => graph.forward_degree(index) + graph.backward_degree(index),
+  }
+}
+
+/// Edges entering a node when walking in `direction`; the reverse of
+/// `out_edges`.
+fn in_edges<G: GraphView>(graph: &G, direction: Direction, index: usize) -> impl Iterator<Item = (usize, bool)> + '_ {
+  let backward = (direction != Direction::Backward).then(|| {
+    graph.backward_neighbors(index).map(|neighbor| (neighbor, graph.is_redirect(neighbor)))
+  });
+  let forward = (direction != Direction::Forward).then(|| {
+    let free = graph.is_redirect(index);
+    graph.forward_neighbors(index).map(move |neighbor| (neighbor, free))
+  });
+  backward.into_iter().flatten().chain(forward.into_iter().flatten())
+}
+
+/// `out_edges` when walking from the start side, `in_edges` from the end
+/// side.
+fn level_edges<G: GraphView>(graph: &G, direction: Direction, forward: bool, index: usize) -> impl Iterator<Item = (usize, bool)> + '_ {
+  let from_start = forward.then(|| out_edges(graph, direction, index));
+  let from_end = (!forward).then(|| in_edges(graph, direction, index));
+  from_start.into_iter().flatten().chain(from_end.into_iter().flatten())
+}
+
+/// Whether `to` can be reached from `from` over `free_links`. Pages linked
+/// for free share a distance, so these are only a few pages around a
+/// redirect.
+fn free_path(free_links: &HashMap<usize, Vec<usize>>, from: usize, to: usize) -> bool {
+  let mut seen = HashSet::from([from]);
+  let mut stack = vec![from];
+  while let Some(index) = stack.pop() {
+    if index == to {
+      return true;
+    }
+    for &next in free_links.get(&index).into_iter().flatten() {
+      if seen.insert(next) {
+        stack.push(next);
       }
     }
+  }
+  false
+}
+
+/// Resolves `|`-separated titles and `#<page id>`s to node indices, also
+/// returning the entries that matched no page.
+pub(crate) fn resolve_pages(
+  title_to_index: &HashMap<String, usize>,
+  id_to_index: &HashMap<u32, usize>,
+  pages: Option<&str>,
+) -> (Vec<usize>, Vec<String>) {
+  let mut found = Vec::new();
+  let mut not_found = Vec::new();
+  for page in pages.unwrap_or("").split('|').map(str::trim).filter(|page| !page.is_empty()) {
+    let index = match page.strip_prefix('#').and_then(|id| id.parse::<u32>().ok()) {
+      Some(id) => id_to_index.get(&id).copied(),
+      None => title_to_index.get(page).copied(),
+    };
+    match index {
+      Some(index) => found.push(index),
+      None => not_found.push(page.to_string()),
+    }
+  }
+  (found, not_found)
+}
 
-    if self.redirected_start.is_some() && !visited[self.start.unwrap()] {
-      visited[self.start.unwrap()] = true;
-      let node = &self.graph.nodes[self.start.unwrap()];
-      let distance = distance_map[self.redirected_start.unwrap()];
-      let neighbor = &self.graph.nodes[self.redirected_start.unwrap()];
+/// The page a search for `index` starts or ends at: its redirect target, or
+/// the page itself. Only one redirect is followed, like a click would.
+pub(crate) fn resolve_redirect<G: GraphView>(graph: &G, index: usize) -> usize {
+  graph.redirect_target(index).unwrap_or(index)
+}
 
-      nodes.push(GraphSearchResultNode::from((node, distance)));
-      edges.push((node.id, neighbor.id));
+/// Which pages a search may go through. Date-related pages and lists are
+/// skipped unless enabled, and avoided pages never pass.
+#[derive(Clone, Default)]
+pub(crate) struct PageFilter {
+  enable_date_related: bool,
+  enable_list_article: bool,
+  /// Includes the targets of avoided redirects.
+  avoided: HashSet<usize>,
+}
+
+impl PageFilter {
+  pub(crate) fn new(enable_date_related: Option<bool>, enable_list_article: Option<bool>) -> Self {
+    PageFilter {
+      enable_date_related: enable_date_related.unwrap_or(false),
+      enable_list_article: enable_list_article.unwrap_or(false),
+      avoided: HashSet::new(),
+    }
+  }
+
+  /// The filter of `query`, also returning the avoided entries that matched
+  /// no page.
+  pub(crate) fn for_query<G: GraphView>(
+    graph: &G,
+    title_to_index: &HashMap<String, usize>,
+    id_to_index: &HashMap<u32, usize>,
+    query: &GraphSearchQuery,
+  ) -> (Self, Vec<String>) {
+    let (avoided, avoid_not_found) = resolve_pages(title_to_index, id_to_index, query.avoid.as_deref());
+    let filter = PageFilter {
+      avoided: avoided
+        .into_iter()
+        .flat_map(|index| std::iter::once(index).chain(graph.redirect_target(index)))
+        .collect(),
+      ..Self::new(query.enable_date_related, query.enable_list_article)
+    };
+    (filter, avoid_not_found)
+  }
+
+  /// Whether every page passes.
+  pub(crate) fn is_empty(&self) -> bool {
+    self.enable_date_related && self.enable_list_article && self.avoided.is_empty()
+  }
+
+  pub(crate) fn allows<G: GraphView>(&self, graph: &G, index: usize) -> bool {
+    if graph.is_date_related(index) && !self.enable_date_related {
+      return false;
     }
+    if graph.is_list_article(index) && !self.enable_list_article {
+      return false;
+    }
+    !self.avoided.contains(&index)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::graph::{ShortestPathsQuery, ShortestPathsResult};
+  use crate::graph::test_graph::TestGraph;
+
+  #[test]
+  fn resolve_pages_by_title_and_id() {
+    let graph = TestGraph::new(&[("A", false), ("B", false)], &[vec![1], vec![]]);
+    let (found, not_found) = resolve_pages(&graph.title_to_index, &graph.id_to_index, Some("B | #1|#9|C||"));
+    assert_eq!(found, vec![1, 0]);
+    assert_eq!(not_found, vec!["#9".to_string(), "C".to_string()]);
+  }
+
+  #[test]
+  fn undirected_route_through_redirect() {
+    // A -> R (redirect) -> T, B -> T
+    let graph = TestGraph::new(
+      &[("A", false), ("R", true), ("T", false), ("B", false)],
+      &[vec![1], vec![2], vec![], vec![2]],
+    );
+    let query = GraphSearchQuery {
+      start: "A".to_string(),
+      end: "B".to_string(),
+      direction: Some(Direction::Undirected),
+      ..Default::default()
+    };
+
+    let result = graph.search(query.clone()).exec();
+    assert!(result.route_found);
+    assert_eq!(result.edges, vec![(1, 2), (2, 3), (3, 4)]);
+    let paths = ShortestPathsResult::new(&result, &ShortestPathsQuery { limit: None, cursor: None }).unwrap();
+    assert_eq!(paths.path_count, 1);
 
-    if self.redirected_end.is_some() && !visited[self.end.unwrap()] {
-      visited[self.end.unwrap()] = true;
-      let node = &self.graph.nodes[self.end.unwrap()];
-      let distance = distance_map[self.redirected_end.unwrap()];
-      let neighbor = &self.graph.nodes[self.redirected_end.unwrap()];
+    let query = GraphSearchQuery { max_nodes: Some(4), ..query };
+    let result = graph.search(query.clone()).exec();
+    assert!(!result.truncated);
+    assert_eq!(result.nodes.len(), 4);
 
-      nodes.push(GraphSearchResultNode::from((node, distance)));
-      edges.push((node.id, neighbor.id));
+    // The other way the redirect is entered from its target.
+    let query = GraphSearchQuery { start: "B".to_string(), end: "A".to_string(), ..query };
+    let result = graph.search(query).exec();
+    assert_eq!(result.edges, vec![(4, 3), (3, 2), (2, 1)]);
+  }
+
+  #[test]
+  fn edges_are_annotated_with_their_direction() {
+    // A -> B <- C
+    let graph = TestGraph::new(&[("A", false), ("B", false), ("C", false)], &[vec![1], vec![], vec![1]]);
+    let search = |start: &str, end: &str, direction: &str| {
+      let query = format!(r#"{{"start":"{}","end":"{}","direction":"{}"}}"#, start, end, direction);
+      graph.search(serde_json::from_str(&query).unwrap()).exec()
+    };
+
+    assert!(!search("B", "A", "forward").route_found);
+    let result = search("B", "A", "in");
+    assert_eq!(result.edges, vec![(2, 1)]);
+    assert_eq!(result.edge_directions, vec![Direction::Backward]);
+
+    assert!(!search("A", "C", "backward").route_found);
+    let result = search("A", "C", "undirected");
+    assert_eq!(result.edges, vec![(1, 2), (2, 3)]);
+    assert_eq!(result.edge_directions, vec![Direction::Forward, Direction::Backward]);
+  }
+
+  fn path_titles(result: &GraphSearchResult) -> Vec<Vec<String>> {
+    let paths = ShortestPathsResult::new(result, &ShortestPathsQuery { limit: None, cursor: None }).unwrap();
+    assert_eq!(paths.path_count, paths.paths.len() as u64);
+    paths.paths.iter().map(|path| path.iter().map(|node| node.title.clone()).collect()).collect()
+  }
+
+  #[test]
+  fn via_route_does_not_pass_a_page_twice() {
+    // S -> X -> V, and from V back through X to E or the long way round.
+    let pages = [("S", false), ("X", false), ("V", false), ("E", false), ("Y", false), ("Z", false)];
+    let mut links = vec![vec![1], vec![2, 3], vec![1, 4], vec![], vec![5], vec![3]];
+    let query = GraphSearchQuery {
+      start: "S".to_string(),
+      end: "E".to_string(),
+      via: Some("V".to_string()),
+      ..Default::default()
+    };
+
+    let result = TestGraph::new(&pages, &links).search(query.clone()).exec();
+    assert!(result.route_found);
+    assert_eq!(result.end_node.as_ref().unwrap().distance, 5);
+    assert_eq!(path_titles(&result), vec![vec!["S", "X", "V", "Y", "Z", "E"]]);
+
+    links[2] = vec![1];
+    let result = TestGraph::new(&pages, &links).search(query).exec();
+    assert!(!result.route_found);
+  }
+
+  #[test]
+  fn via_segment_avoids_later_stops() {
+    // S -> E -> V -> E is shorter than S -> W -> V -> E but passes E twice.
+    let graph = TestGraph::new(
+      &[("S", false), ("E", false), ("V", false), ("W", false)],
+      &[vec![1, 3], vec![2], vec![1], vec![2]],
+    );
+    let result = graph.search(GraphSearchQuery {
+      start: "S".to_string(),
+      end: "E".to_string(),
+      via: Some("V".to_string()),
+      ..Default::default()
+    }).exec();
+    assert!(result.route_found);
+    assert_eq!(path_titles(&result), vec![vec!["S", "W", "V", "E"]]);
+  }
+
+  #[test]
+  fn avoided_pages_force_a_detour() {
+    // S -> A -> E, S -> R (redirect to B) and S -> C -> D -> E, with B -> E.
+    let graph = TestGraph::new(
+      &[("S", false), ("A", false), ("R", true), ("B", false), ("C", false), ("D", false), ("E", false)],
+      &[vec![1, 2, 4], vec![6], vec![3], vec![6], vec![5], vec![6], vec![]],
+    );
+    let search = |avoid: &str| {
+      graph.search(GraphSearchQuery {
+        start: "S".to_string(),
+        end: "E".to_string(),
+        avoid: Some(avoid.to_string()),
+        ..Default::default()
+      }).exec()
+    };
+
+    let result = search("A|Nope");
+    assert_eq!(result.avoid_not_found, vec!["Nope".to_string()]);
+    assert_eq!(path_titles(&result), vec![vec!["S", "R", "B", "E"]]);
+
+    // Avoiding a redirect avoids its target too.
+    let result = search("#2|R");
+    assert_eq!(path_titles(&result), vec![vec!["S", "C", "D", "E"]]);
+
+    // The start and end are never avoided.
+    let result = search("S|E|A|R|C");
+    assert!(!result.route_found);
+    let result = search("S|E");
+    assert_eq!(result.end_node.unwrap().distance, 2);
+  }
+
+  #[test]
+  fn missing_via_page_fails_the_search() {
+    let graph = TestGraph::new(&[("S", false), ("E", false)], &[vec![1], vec![]]);
+    let result = graph.search(GraphSearchQuery {
+      start: "S".to_string(),
+      end: "E".to_string(),
+      via: Some("Nope".to_string()),
+      ..Default::default()
+    }).exec();
+    assert!(!result.route_found);
+    assert_eq!(result.via_not_found, vec!["Nope".to_string()]);
+  }
+
+  /// Fewest links from `from` to `to` walking in `direction`, with links
+  /// out of redirects free.
+  fn reference_distance(graph: &crate::graph::Graph, direction: Direction, from: usize, to: usize) -> Option<u32> {
+    let mut distances = vec![u32::MAX; graph.node_count()];
+    let mut queue = std::collections::VecDeque::from([(from, 0)]);
+    while let Some((index, distance)) = queue.pop_front() {
+      if distance >= distances[index] {
+        continue;
+      }
+      distances[index] = distance;
+      for (neighbor, free) in out_edges(graph, direction, index) {
+        if free {
+          queue.push_front((neighbor, distance));
+        } else {
+          queue.push_back((neighbor, distance + 1));
+        }
+      }
     }
+    Some(distances[to]).filter(|&distance| distance != u32::MAX)
+  }
 
-    (nodes, edges)
+  /// Searches a random graph with redirects once level by level and once
+  /// with every level in parallel, and checks both against a plain 0-1 BFS.
+  #[test]
+  fn parallel_levels_match_sequential() {
+    use rand::{Rng, SeedableRng, rngs::StdRng};
+
+    let mut rng = StdRng::seed_from_u64(7);
+    let count = 300;
+    let titles: Vec<String> = (0..count).map(|index| format!("P{}", index)).collect();
+    let pages: Vec<(&str, bool)> = titles.iter().map(|title| (title.as_str(), rng.random_ratio(1, 4))).collect();
+    // Redirects lead to pages that are not redirects.
+    let regular: Vec<usize> = (0..count).filter(|&index| !pages[index].1).collect();
+    let links: Vec<Vec<usize>> = pages
+      .iter()
+      .map(|&(_, is_redirect)| match is_redirect {
+        true => vec![regular[rng.random_range(0..regular.len())]],
+        false => (0..rng.random_range(0..5)).map(|_| rng.random_range(0..count)).collect(),
+      })
+      .collect();
+    let graph = TestGraph::new(&pages, &links);
+
+    for _ in 0..200 {
+      let (start, end) = (rng.random_range(0..count), rng.random_range(0..count));
+      let direction = [Direction::Forward, Direction::Backward, Direction::Undirected][rng.random_range(0..3)];
+      let query = GraphSearchQuery {
+        start: titles[start].clone(),
+        end: titles[end].clone(),
+        direction: Some(direction),
+        ..Default::default()
+      };
+      let sequential = graph.search(query.clone()).with_parallel_min_edges(usize::MAX).exec();
+      let parallel = graph.search(query).with_parallel_min_edges(0).exec();
+
+      let seed = |index: usize| resolve_redirect(graph.graph.as_ref(), index);
+      let expected = reference_distance(&graph.graph, direction, seed(start), seed(end));
+      for result in [&sequential, &parallel] {
+        let distance = match result.is_start_end_some {
+          true => Some(0),
+          false => result.end_node.as_ref().map(|node| node.distance).filter(|_| result.route_found),
+        };
+        assert_eq!(distance, expected, "{} -> {} {:?}", start, end, direction);
+      }
+      assert_eq!(sequential.route_found, parallel.route_found);
+      let nodes = |result: &GraphSearchResult| {
+        let mut nodes: Vec<(u32, u32)> = result.nodes.iter().map(|node| (node.id, node.distance)).collect();
+        nodes.sort_unstable();
+        nodes
+      };
+      let edges = |result: &GraphSearchResult| {
+        let mut edges = result.edges.clone();
+        edges.sort_unstable();
+        edges
+      };
+      assert_eq!(nodes(&sequential), nodes(&parallel));
+      assert_eq!(edges(&sequential), edges(&parallel));
+    }
   }
-}
\ No newline at end of file
+}
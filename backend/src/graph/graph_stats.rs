@@ -0,0 +1,318 @@
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use crate::graph::GraphView;
+use crate::graph::CancellationToken;
+
+/// Counts and distributions over a whole graph, to see what a `graph.bin`
+/// holds without loading it into a search.
+///
+/// Computing them walks every node and link once. They are kept in a JSON
+/// file next to the graph (see `path_for`).
+#[derive(Serialize, Deserialize)]
+pub struct GraphStats {
+  pub node_count: usize,
+  pub edge_count: usize,
+  /// `GraphView::fingerprint` of the graph they were computed for.
+  pub fingerprint: u64,
+  pub nodes: ClassCounts,
+  /// Links, by the class of the page they lead to.
+  pub edges: ClassCounts,
+  /// Links out of each non-redirect page.
+  pub forward_degree: DegreeDistribution,
+  /// Links into each non-redirect page, including those from redirects.
+  pub backward_degree: DegreeDistribution,
+  /// Non-redirect pages with the most links out, most first.
+  pub top_forward: Vec<PageDegree>,
+  /// Non-redirect pages with the most links in, most first.
+  pub top_backward: Vec<PageDegree>,
+  /// Non-redirect pages nothing links to.
+  pub orphans: PageSample,
+  /// Non-redirect pages that link nowhere.
+  pub dead_ends: PageSample,
+  /// Redirects without a target.
+  pub broken_redirects: PageSample,
+  /// Redirects leading to another redirect.
+  pub redirect_chains: PageSample,
+  /// Most redirects followed from one redirect to reach a page that is not
+  /// a redirect.
+  pub longest_redirect_chain: usize,
+  /// Redirects that never lead to a page that is not a redirect, going
+  /// round in a loop.
+  pub redirect_loops: PageSample,
+  /// Pages linking to themselves.
+  pub self_loops: PageSample,
+  pub duration: std::time::Duration,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+pub struct ClassCounts {
+  pub total: usize,
+  pub redirect: usize,
+  pub date_related: usize,
+  pub list_article: usize,
+  /// Neither redirects, date-related pages nor lists.
+  pub regular: usize,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+pub struct DegreeDistribution {
+  pub min: usize,
+  pub max: usize,
+  pub mean: f64,
+  pub percentiles: Vec<Percentile>,
+  /// `histogram[0]` counts degree 0 and `histogram[k]` degrees from
+  /// `2^(k-1)` up to `2^k - 1`.
+  pub histogram: Vec<usize>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct Percentile {
+  pub percentile: f64,
+  pub degree: usize,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct PageDegree {
+  pub id: u32,
+  pub title: String,
+  pub degree: usize,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+pub struct PageSample {
+  pub count: usize,
+  /// The first `GraphStats::SAMPLE` titles in graph order.
+  pub samples: Vec<String>,
+}
+
+impl GraphStats {
+  pub const DEFAULT_TOP: usize = 20;
+  pub const SAMPLE: usize = 20;
+  pub const PERCENTILES: [f64; 6] = [25.0, 50.0, 75.0, 90.0, 99.0, 99.9];
+  /// Chains longer than this are taken for loops.
+  pub const MAX_REDIRECT_CHAIN: usize = 64;
+
+  /// `graph.bin` becomes `graph.stats.json`.
+  pub fn path_for(graph_path: &str) -> String {
+    match graph_path.strip_suffix(".bin") {
+      Some(stem) => format!("{}.stats.json", stem),
+      None => format!("{}.stats.json", graph_path),
+    }
+  }
+
+  /// Whether these stats were computed for `graph`.
+  pub fn matches<G: GraphView>(&self, graph: &G) -> bool {
+    self.node_count == graph.node_count()
+      && self.edge_count == graph.edge_count()
+      && self.fingerprint == graph.fingerprint()
+  }
+
+  /// Keeps the `top` pages with the most links each way.
+  pub fn compute<G: GraphView>(graph: &G, top: usize) -> Self {
+    Self::compute_with_cancellation(graph, top, &CancellationToken::new()).unwrap()
+  }
+
+  /// Like `compute`, but gives up with `None` once `cancellation` is
+  /// cancelled.
+  pub fn compute_with_cancellation<G: GraphView>(graph: &G, top: usize, cancellation: &CancellationToken) -> Option<Self> {
+    let start_time = std::time::Instant::now();
+    let node_count = graph.node_count();
+
+    let mut nodes = ClassCounts::default();
+    let mut edges = ClassCounts::default();
+    let mut orphans = PageSample::default();
+    let mut dead_ends = PageSample::default();
+    let mut broken_redirects = PageSample::default();
+    let mut redirect_chains = PageSample::default();
+    let mut redirect_loops = PageSample::default();
+    let mut self_loops = PageSample::default();
+    let mut longest_redirect_chain = 0;
+    let mut forward_degrees = Vec::new();
+    let mut backward_degrees = Vec::new();
+
+    for index in 0..node_count {
+      if index % 4096 == 0 && cancellation.is_cancelled() {
+        return None;
+      }
+      nodes.add(graph, index);
+      for neighbor in graph.forward_neighbors(index) {
+        edges.add(graph, neighbor);
+        if neighbor == index {
+          self_loops.add(graph, index);
+        }
+      }
+
+      if graph.is_redirect(index) {
+        match graph.redirect_target(index) {
+          None => broken_redirects.add(graph, index),
+          Some(target) if graph.is_redirect(target) => redirect_chains.add(graph, index),
+          Some(_) => {},
+        }
+        match redirect_chain_length(graph, index) {
+          Some(length) => longest_redirect_chain = longest_redirect_chain.max(length),
+          None => redirect_loops.add(graph, index),
+        }
+        continue;
+      }
+
+      let forward_degree = graph.forward_degree(index);
+      let backward_degree = graph.backward_degree(index);
+      if backward_degree == 0 {
+        orphans.add(graph, index);
+      }
+      if forward_degree == 0 {
+        dead_ends.add(graph, index);
+      }
+      forward_degrees.push((forward_degree, index));
+      backward_degrees.push((backward_degree, index));
+    }
+
+    Some(GraphStats {
+      node_count,
+      edge_count: edges.total,
+      fingerprint: graph.fingerprint(),
+      nodes,
+      edges,
+      top_forward: top_pages(graph, &mut forward_degrees, top),
+      top_backward: top_pages(graph, &mut backward_degrees, top),
+      forward_degree: DegreeDistribution::new(&mut forward_degrees),
+      backward_degree: DegreeDistribution::new(&mut backward_degrees),
+      orphans,
+      dead_ends,
+      broken_redirects,
+      redirect_chains,
+      longest_redirect_chain,
+      redirect_loops,
+      self_loops,
+      duration: start_time.elapsed(),
+    })
+  }
+}
+
+impl std::fmt::Display for GraphStats {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    let classes = |counts: &ClassCounts| format!(
+      "{} ({} redirect, {} date-related, {} list, {} regular)",
+      counts.total, counts.redirect, counts.date_related, counts.list_article, counts.regular,
+    );
+    writeln!(f, "Nodes: {}", classes(&self.nodes))?;
+    writeln!(f, "Edges: {}", classes(&self.edges))?;
+
+    for (name, distribution) in [("Out-degree", &self.forward_degree), ("In-degree", &self.backward_degree)] {
+      let percentiles: Vec<String> = distribution.percentiles
+        .iter()
+        .map(|p| format!("p{}={}", p.percentile, p.degree))
+        .collect();
+      writeln!(
+        f,
+        "{}: min={} mean={:.2} max={} {}",
+        name, distribution.min, distribution.mean, distribution.max, percentiles.join(" "),
+      )?;
+    }
+
+    writeln!(f, "Orphans: {}", self.orphans.count)?;
+    writeln!(f, "Dead ends: {}", self.dead_ends.count)?;
+    writeln!(f, "Broken redirects: {}", self.broken_redirects.count)?;
+    writeln!(f, "Redirect chains: {} (longest {})", self.redirect_chains.count, self.longest_redirect_chain)?;
+    writeln!(f, "Redirect loops: {}", self.redirect_loops.count)?;
+    writeln!(f, "Self-loops: {}", self.self_loops.count)?;
+
+    for (name, pages) in [("Most links out", &self.top_forward), ("Most links in", &self.top_backward)] {
+      if !pages.is_empty() {
+        writeln!(f, "\n{}:", name)?;
+        for page in pages {
+          writeln!(f, "  {} {} ({})", page.degree, page.title, page.id)?;
+        }
+      }
+    }
+    Ok(())
+  }
+}
+
+impl ClassCounts {
+  fn add<G: GraphView>(&mut self, graph: &G, index: usize) {
+    self.total += 1;
+    let (redirect, date_related, list_article) =
+      (graph.is_redirect(index), graph.is_date_related(index), graph.is_list_article(index));
+    self.redirect += redirect as usize;
+    self.date_related += date_related as usize;
+    self.list_article += list_article as usize;
+    self.regular += !(redirect || date_related || list_article) as usize;
+  }
+}
+
+impl DegreeDistribution {
+  /// Sorts `degrees` by degree.
+  fn new(degrees: &mut [(usize, usize)]) -> Self {
+    if degrees.is_empty() {
+      return DegreeDistribution::default();
+    }
+    degrees.par_sort_unstable();
+
+    let mut histogram = Vec::new();
+    for &(degree, _) in degrees.iter() {
+      let bucket = (usize::BITS - degree.leading_zeros()) as usize;
+      if histogram.len() <= bucket {
+        histogram.resize(bucket + 1, 0);
+      }
+      histogram[bucket] += 1;
+    }
+
+    // Nearest-rank percentiles.
+    let percentiles = GraphStats::PERCENTILES
+      .iter()
+      .map(|&percentile| {
+        let rank = (percentile / 100.0 * degrees.len() as f64).ceil() as usize;
+        Percentile { percentile, degree: degrees[rank.clamp(1, degrees.len()) - 1].0 }
+      })
+      .collect();
+
+    DegreeDistribution {
+      min: degrees[0].0,
+      max: degrees[degrees.len() - 1].0,
+      mean: degrees.iter().map(|&(degree, _)| degree as f64).sum::<f64>() / degrees.len() as f64,
+      percentiles,
+      histogram,
+    }
+  }
+}
+
+impl PageSample {
+  fn add<G: GraphView>(&mut self, graph: &G, index: usize) {
+    self.count += 1;
+    if self.samples.len() < GraphStats::SAMPLE {
+      self.samples.push(graph.title(index).to_string());
+    }
+  }
+}
+
+/// The `top` pages with the highest degree, ties broken by graph order.
+fn top_pages<G: GraphView>(graph: &G, degrees: &mut [(usize, usize)], top: usize) -> Vec<PageDegree> {
+  let top = top.min(degrees.len());
+  if top == 0 {
+    return Vec::new();
+  }
+  let by_degree = |a: &(usize, usize), b: &(usize, usize)| b.0.cmp(&a.0).then(a.1.cmp(&b.1));
+  degrees.select_nth_unstable_by(top - 1, by_degree);
+  let mut pages = degrees[..top].to_vec();
+  pages.sort_unstable_by(by_degree);
+  pages
+    .into_iter()
+    .map(|(degree, index)| PageDegree { id: graph.id(index), title: graph.title(index).to_string(), degree })
+    .collect()
+}
+
+/// Redirects followed from `index` to reach a page that is not one, or
+/// `None` when they go round in a loop.
+fn redirect_chain_length<G: GraphView>(graph: &G, index: usize) -> Option<usize> {
+  let mut length = 0;
+  let mut current = index;
+  while let Some(target) = graph.redirect_target(current) {
+    length += 1;
+    if length > GraphStats::MAX_REDIRECT_CHAIN {
+      return None;
+    }
+    current = target;
+  }
+  Some(length)
+}
@@ -0,0 +1,142 @@
+/// Read-only access to a directed link graph.
+///
+/// Search code only talks to the graph through this trait, so alternative
+/// storage layouts (memory mapped, compressed, filtered subgraphs) can be
+/// plugged in without touching the algorithms. Nodes are addressed by a dense
+/// index in `0..node_count()`.
+pub trait GraphView: Send + Sync {
+  type Neighbors<'a>: Iterator<Item = usize> + 'a where Self: 'a;
+
+  fn node_count(&self) -> usize;
+  fn id(&self, index: usize) -> u32;
+  fn ns(&self, index: usize) -> i32;
+  fn title(&self, index: usize) -> &str;
+  fn is_redirect(&self, index: usize) -> bool;
+  fn is_date_related(&self, index: usize) -> bool;
+  fn is_list_article(&self, index: usize) -> bool;
+
+  /// Nodes this node links to.
+  fn forward_neighbors(&self, index: usize) -> Self::Neighbors<'_>;
+
+  /// Nodes linking to this node.
+  fn backward_neighbors(&self, index: usize) -> Self::Neighbors<'_>;
+
+  fn forward_degree(&self, index: usize) -> usize {
+    self.forward_neighbors(index).count()
+  }
+
+  fn backward_degree(&self, index: usize) -> usize {
+    self.backward_neighbors(index).count()
+  }
+
+  /// The page a redirect points to, or `None` for regular pages.
+  fn redirect_target(&self, index: usize) -> Option<usize> {
+    if self.is_redirect(index) {
+      self.forward_neighbors(index).next()
+    } else {
+      None
+    }
+  }
+
+  /// Number of links.
+  fn edge_count(&self) -> usize {
+    (0..self.node_count()).map(|index| self.forward_degree(index)).sum()
+  }
+
+  /// Position of the first link of a node when all links are numbered node
+  /// by node, as files kept next to the graph lay them out.
+  fn forward_edge_start(&self, index: usize) -> usize {
+    (0..index).map(|index| self.forward_degree(index)).sum()
+  }
+
+  /// A hash of the pages and their links, to tell whether a file written
+  /// for a graph still belongs to it. Stays the same across builds.
+  fn fingerprint(&self) -> u64 {
+    link_fingerprint(self)
+  }
+}
+
+/// FNV-1a over every page id and its links, which walks the whole graph.
+fn link_fingerprint<G: GraphView + ?Sized>(graph: &G) -> u64 {
+  const OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+  const PRIME: u64 = 0x100000001b3;
+  let mut hash = OFFSET_BASIS;
+  let mut write = |value: u64| {
+    for byte in value.to_le_bytes() {
+      hash = (hash ^ byte as u64).wrapping_mul(PRIME);
+    }
+  };
+  write(graph.node_count() as u64);
+  for index in 0..graph.node_count() {
+    write(graph.id(index) as u64);
+    write(graph.forward_degree(index) as u64);
+    for neighbor_index in graph.forward_neighbors(index) {
+      write(neighbor_index as u64);
+    }
+  }
+  hash
+}
+
+impl GraphView for crate::graph::Graph {
+  type Neighbors<'a> = std::iter::Copied<std::slice::Iter<'a, usize>>;
+
+  fn node_count(&self) -> usize {
+    self.nodes.len()
+  }
+
+  fn id(&self, index: usize) -> u32 {
+    self.nodes[index].id
+  }
+
+  fn ns(&self, index: usize) -> i32 {
+    self.nodes[index].ns
+  }
+
+  fn title(&self, index: usize) -> &str {
+    &self.nodes[index].title
+  }
+
+  fn is_redirect(&self, index: usize) -> bool {
+    self.nodes[index].is_redirect
+  }
+
+  fn is_date_related(&self, index: usize) -> bool {
+    self.nodes[index].is_date_related
+  }
+
+  fn is_list_article(&self, index: usize) -> bool {
+    self.nodes[index].is_list_article
+  }
+
+  fn forward_neighbors(&self, index: usize) -> Self::Neighbors<'_> {
+    let (s, e) = self.nodes[index].forward_edge_range;
+    self.forward_edges[s..e].iter().copied()
+  }
+
+  fn backward_neighbors(&self, index: usize) -> Self::Neighbors<'_> {
+    let (s, e) = self.nodes[index].backward_edge_range;
+    self.backward_edges[s..e].iter().copied()
+  }
+
+  fn forward_degree(&self, index: usize) -> usize {
+    let (s, e) = self.nodes[index].forward_edge_range;
+    e - s
+  }
+
+  fn backward_degree(&self, index: usize) -> usize {
+    let (s, e) = self.nodes[index].backward_edge_range;
+    e - s
+  }
+
+  fn edge_count(&self) -> usize {
+    self.forward_edges.len()
+  }
+
+  fn forward_edge_start(&self, index: usize) -> usize {
+    self.nodes[index].forward_edge_range.0
+  }
+
+  fn fingerprint(&self) -> u64 {
+    *self.fingerprint.get_or_init(|| link_fingerprint(self))
+  }
+}
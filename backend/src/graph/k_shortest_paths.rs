@@ -0,0 +1,430 @@
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, HashSet};
+use std::sync::Arc;
+use serde::{Deserialize, Serialize};
+use crate::graph::GraphView;
+use crate::graph::{CancellationToken, SearchWorkspace};
+use crate::graph::{Budget, GraphSearchQuery, GraphSearchResultNode};
+use crate::graph::distance_map::next_distance;
+use crate::graph::graph_search::{resolve_pages, resolve_redirect, PageFilter};
+
+/// The `k` shortest simple paths between two pages (Yen's algorithm),
+/// counting hops out of redirects as 0 like `GraphSearch` does.
+///
+/// Paths run from the redirect target of a redirected start to the redirect
+/// target of a redirected end, with the requested start page put in front.
+/// With several starts or ends, paths may run between any of them. Paths of
+/// equal length come in a fixed order, so results are stable for the same
+/// graph.
+///
+/// Every spur path is a search of its own; `max_visited`, `timeout_ms` and
+/// cancellation bound all of them together, and `max_depth` caps the length
+/// of the paths. Queries setting `GraphSearchQuery::unsupported_parameter`
+/// are meant to be rejected before searching.
+pub struct KShortestPaths<G: GraphView = crate::graph::Graph> {
+  graph: Arc<G>,
+  query: GraphSearchQuery,
+  workspace: SearchWorkspace,
+  filter: PageFilter,
+  avoid_not_found: Vec<String>,
+  /// Requested start and end pages; empty when any of them was not found.
+  starts: Vec<usize>,
+  ends: Vec<usize>,
+  visited_nodes: u32,
+  cancellation: Option<CancellationToken>,
+  deadline: Option<std::time::Instant>,
+  budget_checks: u32,
+  budget_exhausted: Option<Budget>,
+  /// Set when a path was cut short by `max_depth`.
+  depth_limited: bool,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct KShortestPathsQuery {
+  pub k: Option<usize>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct KShortestPathsResult {
+  pub start_not_found: bool,
+  pub end_not_found: bool,
+  pub route_found: bool,
+  pub start_node: Option<GraphSearchResultNode>,
+  pub end_node: Option<GraphSearchResultNode>,
+  pub avoid_not_found: Vec<String>,
+  /// Shortest first. Each node's `distance` is its distance along that path.
+  pub paths: Vec<KShortestPath>,
+  pub visited_nodes: u32,
+  /// Set when fewer than `k` paths were found because of a limit.
+  pub budget_exhausted: Option<Budget>,
+  pub duration: std::time::Duration,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct KShortestPath {
+  pub distance: u32,
+  pub nodes: Vec<GraphSearchResultNode>,
+}
+
+impl KShortestPathsQuery {
+  pub const DEFAULT_K: usize = 3;
+  pub const MAX_K: usize = 20;
+}
+
+impl<G: GraphView> KShortestPaths<G> {
+  /// Searches in a cleared workspace, typically taken from a
+  /// `SearchWorkspacePool`.
+  pub fn with_workspace(
+    graph: Arc<G>,
+    title_to_index: Arc<HashMap<String, usize>>,
+    id_to_index: Arc<HashMap<u32, usize>>,
+    query: GraphSearchQuery,
+    workspace: SearchWorkspace,
+  ) -> Self {
+    debug_assert_eq!(workspace.node_count(), graph.node_count());
+
+    let (starts, starts_not_found) = resolve_pages(&title_to_index, &id_to_index, Some(&query.start));
+    let starts = if starts_not_found.is_empty() { starts } else { Vec::new() };
+    let (ends, ends_not_found) = resolve_pages(&title_to_index, &id_to_index, Some(&query.end));
+    let ends = if ends_not_found.is_empty() { ends } else { Vec::new() };
+
+    let (filter, avoid_not_found) = PageFilter::for_query(graph.as_ref(), &title_to_index, &id_to_index, &query);
+
+    KShortestPaths {
+      graph,
+      query,
+      workspace,
+      filter,
+      avoid_not_found,
+      starts,
+      ends,
+      visited_nodes: 0,
+      cancellation: None,
+      deadline: None,
+      budget_checks: 0,
+      budget_exhausted: None,
+      depth_limited: false,
+    }
+  }
+
+  /// Stops the search early once `cancellation` is cancelled.
+  pub fn with_cancellation(mut self, cancellation: CancellationToken) -> Self {
+    self.cancellation = Some(cancellation);
+    self
+  }
+
+  pub fn exec(&mut self, k_query: &KShortestPathsQuery) -> KShortestPathsResult {
+    let start_time = std::time::Instant::now();
+    let k = k_query.k.unwrap_or(KShortestPathsQuery::DEFAULT_K).min(KShortestPathsQuery::MAX_K);
+
+    if self.starts.is_empty() || self.ends.is_empty() {
+      return KShortestPathsResult {
+        start_not_found: self.starts.is_empty(),
+        end_not_found: self.ends.is_empty(),
+        route_found: false,
+        start_node: None,
+        end_node: None,
+        avoid_not_found: self.avoid_not_found.clone(),
+        paths: Vec::new(),
+        visited_nodes: 0,
+        budget_exhausted: None,
+        duration: start_time.elapsed(),
+      };
+    }
+    self.deadline = self.query.timeout_ms.map(|timeout| start_time + std::time::Duration::from_millis(timeout));
+
+    // The first requested page for each page searched from or to.
+    let mut start_for: HashMap<usize, usize> = HashMap::new();
+    for &start in &self.starts {
+      start_for.entry(resolve_redirect(self.graph.as_ref(), start)).or_insert(start);
+    }
+    let mut end_for: HashMap<usize, usize> = HashMap::new();
+    for &end in &self.ends {
+      end_for.entry(resolve_redirect(self.graph.as_ref(), end)).or_insert(end);
+    }
+    let mut sources: Vec<usize> = start_for.keys().copied().collect();
+    sources.sort_unstable();
+    let targets: HashSet<usize> = end_for.keys().copied().collect();
+
+    let paths = if k == 0 {
+      Vec::new()
+    } else {
+      self.yen(&sources, &targets, k)
+    };
+    if self.budget_exhausted.is_none() && self.depth_limited && paths.len() < k {
+      self.budget_exhausted = Some(Budget::MaxDepth);
+    }
+
+    let (start, end) = match paths.first() {
+      Some((_, path)) => (start_for[&path[0]], end_for[&path[path.len() - 1]]),
+      None => (self.starts[0], self.ends[0]),
+    };
+    let paths: Vec<KShortestPath> = paths
+      .into_iter()
+      .map(|(distance, mut path)| {
+        let start = start_for[&path[0]];
+        if path[0] != start {
+          path.insert(0, start);
+        }
+        KShortestPath { distance, nodes: self.result_nodes(&path) }
+      })
+      .collect();
+
+    let distance = paths.first().map(|path| path.distance).unwrap_or(crate::graph::UNVISITED);
+    KShortestPathsResult {
+      start_not_found: false,
+      end_not_found: false,
+      route_found: !paths.is_empty(),
+      start_node: Some(GraphSearchResultNode::new(self.graph.as_ref(), start, 0)),
+      end_node: Some(GraphSearchResultNode::new(self.graph.as_ref(), end, distance)),
+      avoid_not_found: self.avoid_not_found.clone(),
+      paths,
+      visited_nodes: self.visited_nodes,
+      budget_exhausted: self.budget_exhausted,
+      duration: start_time.elapsed(),
+    }
+  }
+
+  /// Hands the workspace back, e.g. to return it to its pool.
+  pub fn into_workspace(mut self) -> SearchWorkspace {
+    self.workspace.clear();
+    self.workspace
+  }
+
+  /// Yen's algorithm, with every source hanging off one virtual root so
+  /// that paths from the other sources are spur paths of the root.
+  fn yen(&mut self, sources: &[usize], targets: &HashSet<usize>, k: usize) -> Vec<(u32, Vec<usize>)> {
+    let mut found: Vec<(u32, Vec<usize>)> = Vec::new();
+    let Some(first) = self.shortest_path(sources, targets, &HashSet::new(), &HashSet::new()) else {
+      return found;
+    };
+    let mut seen: HashSet<Vec<usize>> = HashSet::new();
+    seen.insert(first.clone());
+    found.push((self.cost(&first), first));
+
+    let mut candidates = BinaryHeap::new();
+    while found.len() < k && !self.out_of_budget() {
+      let previous = found.last().unwrap().1.clone();
+      // The root's spur only changes when a path leaves from a new source.
+      let new_source = found.iter().filter(|(_, path)| path[0] == previous[0]).count() == 1;
+      let used_sources: HashSet<usize> = found.iter().map(|(_, path)| path[0]).collect();
+      let spur_sources: Vec<usize> = sources.iter().copied().filter(|source| !used_sources.contains(source)).collect();
+      if new_source && !spur_sources.is_empty() {
+        if let Some(path) = self.shortest_path(&spur_sources, targets, &HashSet::new(), &HashSet::new()) {
+          if seen.insert(path.clone()) {
+            candidates.push(Reverse((self.cost(&path), path)));
+          }
+        }
+      }
+
+      for i in 0..previous.len() - 1 {
+        let spur = previous[i];
+        let root = &previous[..=i];
+
+        let banned_edges: HashSet<(usize, usize)> = found
+          .iter()
+          .filter(|(_, path)| path.len() > i + 1 && &path[..=i] == root)
+          .map(|(_, path)| (path[i], path[i + 1]))
+          .collect();
+        let banned_nodes: HashSet<usize> = root[..i].iter().copied().collect();
+
+        let Some(spur_path) = self.shortest_path(&[spur], targets, &banned_nodes, &banned_edges) else {
+          continue;
+        };
+        let mut path = root[..i].to_vec();
+        path.extend(spur_path);
+        if seen.insert(path.clone()) {
+          candidates.push(Reverse((self.cost(&path), path)));
+        }
+      }
+
+      match candidates.pop() {
+        Some(Reverse(candidate)) => found.push(candidate),
+        None => break,
+      }
+    }
+    found
+  }
+
+  /// 0-1 BFS from the nearest of `sources` to the nearest of `targets` that
+  /// avoids the banned nodes and edges, returning the path as node indices.
+  fn shortest_path(
+    &mut self,
+    sources: &[usize],
+    targets: &HashSet<usize>,
+    banned_nodes: &HashSet<usize>,
+    banned_edges: &HashSet<(usize, usize)>,
+  ) -> Option<Vec<usize>> {
+    self.workspace.clear();
+    let mut parents: HashMap<usize, usize> = HashMap::new();
+    let max_depth = self.query.max_depth.unwrap_or(crate::graph::UNVISITED);
+
+    for &source in sources {
+      self.workspace.front_distance_map.set(source, 0);
+      self.workspace.front_queue.push_back(source);
+    }
+    let mut reached = None;
+    while let Some(node_index) = self.workspace.front_queue.pop_front() {
+      if self.out_of_budget() {
+        return None;
+      }
+      let distance_map = &mut self.workspace.front_distance_map;
+      let done = &mut self.workspace.visited;
+      let queue = &mut self.workspace.front_queue;
+      if done.is_visited(node_index) {
+        continue;
+      }
+      done.set(node_index, 0);
+      self.visited_nodes += 1;
+      if targets.contains(&node_index) {
+        reached = Some(node_index);
+        break;
+      }
+
+      let distance = distance_map.get(node_index);
+      let node_is_redirect = self.graph.is_redirect(node_index);
+      for neighbor_index in self.graph.forward_neighbors(node_index) {
+        if done.is_visited(neighbor_index)
+          || banned_nodes.contains(&neighbor_index)
+          || banned_edges.contains(&(node_index, neighbor_index))
+        {
+          continue;
+        }
+        if !targets.contains(&neighbor_index) && !self.filter.allows(self.graph.as_ref(), neighbor_index) {
+          continue;
+        }
+
+        let neighbor_distance = if node_is_redirect {
+          distance
+        } else {
+          match next_distance(distance) {
+            Some(next) => next,
+            None => continue,
+          }
+        };
+        if neighbor_distance > max_depth {
+          self.depth_limited = true;
+          continue;
+        }
+        if neighbor_distance >= distance_map.get(neighbor_index) {
+          continue;
+        }
+        distance_map.set(neighbor_index, neighbor_distance);
+        parents.insert(neighbor_index, node_index);
+        if node_is_redirect {
+          queue.push_front(neighbor_index);
+        } else {
+          queue.push_back(neighbor_index);
+        }
+      }
+    }
+
+    let target = reached?;
+    let mut path = vec![target];
+    let mut node_index = target;
+    while let Some(&parent) = parents.get(&node_index) {
+      node_index = parent;
+      path.push(node_index);
+    }
+    path.reverse();
+    Some(path)
+  }
+
+  /// Whether `max_visited`, the deadline or cancellation stops the search,
+  /// remembering which one did. Like `GraphSearch`, the clock is only read
+  /// every so many checks.
+  fn out_of_budget(&mut self) -> bool {
+    if self.budget_exhausted.is_some() {
+      return true;
+    }
+    self.budget_checks = self.budget_checks.wrapping_add(1);
+    let budget = if self.query.max_visited.is_some_and(|max| self.visited_nodes >= max) {
+      Budget::MaxVisited
+    } else if self.cancellation.as_ref().is_some_and(|cancellation| cancellation.is_cancelled()) {
+      Budget::Cancelled
+    } else if self.deadline.is_some_and(|deadline| self.budget_checks % 256 == 0 && std::time::Instant::now() >= deadline) {
+      Budget::Timeout
+    } else {
+      return false;
+    };
+    self.budget_exhausted = Some(budget);
+    true
+  }
+
+  fn cost(&self, path: &[usize]) -> u32 {
+    path[..path.len() - 1]
+      .iter()
+      .filter(|&&index| !self.graph.is_redirect(index))
+      .count() as u32
+  }
+
+  fn result_nodes(&self, path: &[usize]) -> Vec<GraphSearchResultNode> {
+    let mut distance = 0;
+    let mut nodes = Vec::with_capacity(path.len());
+    for (i, &index) in path.iter().enumerate() {
+      if i > 0 && !self.graph.is_redirect(path[i - 1]) {
+        distance += 1;
+      }
+      nodes.push(GraphSearchResultNode::new(self.graph.as_ref(), index, distance));
+    }
+    nodes
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::graph::test_graph::TestGraph;
+
+  fn k_paths(graph: &TestGraph, start: &str, end: &str, avoid: Option<&str>, k: usize) -> KShortestPathsResult {
+    let query = GraphSearchQuery {
+      start: start.to_string(),
+      end: end.to_string(),
+      avoid: avoid.map(str::to_string),
+      ..Default::default()
+    };
+    let workspace = SearchWorkspace::new(graph.graph.node_count());
+    KShortestPaths::with_workspace(graph.graph.clone(), graph.title_to_index.clone(), graph.id_to_index.clone(), query, workspace)
+      .exec(&KShortestPathsQuery { k: Some(k) })
+  }
+
+  fn titles(path: &KShortestPath) -> Vec<&str> {
+    path.nodes.iter().map(|node| node.title.as_str()).collect()
+  }
+
+  /// A -> B -> E, A -> R (redirect) -> C -> E and A -> C.
+  fn diamond() -> TestGraph {
+    TestGraph::new(
+      &[("A", false), ("B", false), ("R", true), ("C", false), ("E", false)],
+      &[vec![1, 2, 3], vec![4], vec![3], vec![4], vec![]],
+    )
+  }
+
+  #[test]
+  fn all_simple_paths_shortest_first() {
+    let result = k_paths(&diamond(), "A", "E", None, 5);
+    assert!(result.route_found);
+    assert!(result.paths.iter().all(|path| path.distance == 2));
+    let mut paths: Vec<Vec<&str>> = result.paths.iter().map(titles).collect();
+    paths.sort();
+    assert_eq!(paths, vec![vec!["A", "B", "E"], vec!["A", "C", "E"], vec!["A", "R", "C", "E"]]);
+
+    let result = k_paths(&diamond(), "A", "E", None, 1);
+    assert_eq!(result.paths.len(), 1);
+  }
+
+  #[test]
+  fn avoided_pages_are_reported() {
+    let result = k_paths(&diamond(), "A", "E", Some("B|Nope"), 5);
+    assert_eq!(result.avoid_not_found, vec!["Nope".to_string()]);
+    assert!(result.paths.iter().all(|path| !titles(path).contains(&"B")));
+    assert_eq!(result.paths.len(), 2);
+  }
+
+  #[test]
+  fn missing_start_fails_the_search() {
+    let result = k_paths(&diamond(), "A|Nope", "E", None, 5);
+    assert!(result.start_not_found && !result.end_not_found);
+    assert!(!result.route_found && result.paths.is_empty());
+  }
+}
@@ -1,9 +1,83 @@
 mod node;
+#[allow(clippy::module_inception)]
 mod graph;
+mod graph_view;
+mod edge_attributes;
+mod distance_map;
+mod atomic_bitmap;
+mod search_workspace;
+mod distance_oracle;
+mod cancellation;
+mod subgraph;
 mod graph_search;
+mod shortest_path_dag;
+mod k_shortest_paths;
+mod neighborhood;
+mod reachability;
+mod weighted_search;
+mod batch_search;
+mod random_pairs;
+mod click_path;
+mod graph_stats;
+#[cfg(test)]
+mod test_graph;
 
 pub use node::Node;
 pub use graph::Graph;
+pub use graph_view::GraphView;
+pub use edge_attributes::EdgeAttribute;
+pub use edge_attributes::EdgeAttributes;
+pub use distance_map::DistanceMap;
+pub use distance_map::UNVISITED;
+pub use search_workspace::SearchWorkspace;
+pub use search_workspace::SearchWorkspacePool;
+pub use distance_oracle::DistanceOracle;
+pub use distance_oracle::DistanceBounds;
+pub use distance_oracle::DistanceBoundsQuery;
+pub use distance_oracle::DistanceBoundsResult;
+pub use distance_oracle::LandmarkSelection;
+pub use cancellation::CancellationToken;
+pub use subgraph::Subgraph;
 pub use graph_search::GraphSearch;
 pub use graph_search::GraphSearchResult;
-pub use graph_search::GraphSearchQuery;
\ No newline at end of file
+pub use graph_search::GraphSearchQuery;
+pub use graph_search::GraphSearchResultNode;
+pub use graph_search::RankBy;
+pub use graph_search::Direction;
+pub use graph_search::Budget;
+pub use graph_search::SearchProgress;
+pub use shortest_path_dag::ShortestPathDag;
+pub use shortest_path_dag::ShortestPathsQuery;
+pub use shortest_path_dag::ShortestPathsResult;
+pub use k_shortest_paths::KShortestPaths;
+pub use k_shortest_paths::KShortestPathsQuery;
+pub use k_shortest_paths::KShortestPathsResult;
+pub use k_shortest_paths::KShortestPath;
+pub use neighborhood::Neighborhood;
+pub use neighborhood::NeighborhoodQuery;
+pub use neighborhood::NeighborhoodResult;
+pub use neighborhood::Sampling;
+pub use reachability::Reachability;
+pub use reachability::ReachabilityQuery;
+pub use reachability::ReachabilityResult;
+pub use reachability::DistanceHistogram;
+pub use weighted_search::WeightedSearch;
+pub use weighted_search::EdgeCostQuery;
+pub use batch_search::BatchSearch;
+pub use batch_search::BatchSearchQuery;
+pub use batch_search::BatchSearchItem;
+pub use batch_search::BatchSearchResult;
+pub use random_pairs::RandomPairs;
+pub use random_pairs::RandomPairsQuery;
+pub use random_pairs::RandomPairsResult;
+pub use random_pairs::RandomPair;
+pub use click_path::ClickPath;
+pub use click_path::ClickPathQuery;
+pub use click_path::ClickPathResult;
+pub use click_path::ClickPathStep;
+pub use graph_stats::GraphStats;
+pub use graph_stats::ClassCounts;
+pub use graph_stats::DegreeDistribution;
+pub use graph_stats::Percentile;
+pub use graph_stats::PageDegree;
+pub use graph_stats::PageSample;
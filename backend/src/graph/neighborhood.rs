@@ -0,0 +1,215 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use rand::{SeedableRng, rngs::StdRng, seq::SliceRandom};
+use serde::{Deserialize, Serialize};
+use crate::graph::GraphView;
+use crate::graph::{CancellationToken, Direction};
+use crate::graph::GraphSearchResultNode;
+use crate::graph::graph_search::{resolve_pages, resolve_redirect, PageFilter};
+
+/// The pages within a few links of one page.
+///
+/// Redirects are collapsed: a link to a redirect counts as a link to its
+/// target, and a page linking through a redirect counts as linking to the
+/// target directly. Like in `GraphSearch`, only one redirect is followed.
+///
+/// When a hop would bring the total past `limit` pages, only as many of
+/// the new pages as still fit are kept, chosen by `sampling`, and only
+/// those are expanded further.
+pub struct Neighborhood<G: GraphView = crate::graph::Graph> {
+  graph: Arc<G>,
+  query: NeighborhoodQuery,
+  filter: PageFilter,
+  center: Option<usize>,
+  cancellation: Option<CancellationToken>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Sampling {
+  /// Keep the pages with the most links.
+  #[default]
+  Degree,
+  /// Keep a random sample, reproducible with `seed`.
+  Random,
+}
+
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub struct NeighborhoodQuery {
+  /// A title or `#<page id>`.
+  pub title: String,
+  pub depth: Option<u32>,
+  pub direction: Option<Direction>,
+  /// Largest number of pages to return, including the center.
+  pub limit: Option<usize>,
+  pub sampling: Option<Sampling>,
+  pub seed: Option<u64>,
+  pub enable_date_related: Option<bool>,
+  pub enable_list_article: Option<bool>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct NeighborhoodResult {
+  pub not_found: bool,
+  /// The requested page, or its target when it is a redirect.
+  pub center: Option<GraphSearchResultNode>,
+  /// Each node's `distance` is its hop count from the center.
+  pub nodes: Vec<GraphSearchResultNode>,
+  /// Links between the returned pages, in link direction.
+  pub edges: Vec<(u32, u32)>,
+  /// Pages found before sampling, up to the depth that was explored.
+  pub discovered_nodes: usize,
+  pub sampled: bool,
+  pub duration: std::time::Duration,
+}
+
+impl NeighborhoodQuery {
+  pub const DEFAULT_DEPTH: u32 = 1;
+  pub const MAX_DEPTH: u32 = 3;
+  pub const DEFAULT_LIMIT: usize = 200;
+  pub const MAX_LIMIT: usize = 2000;
+}
+
+impl<G: GraphView> Neighborhood<G> {
+  pub fn new(
+    graph: Arc<G>,
+    title_to_index: Arc<HashMap<String, usize>>,
+    id_to_index: Arc<HashMap<u32, usize>>,
+    query: NeighborhoodQuery,
+  ) -> Self {
+    let (found, not_found) = resolve_pages(&title_to_index, &id_to_index, Some(&query.title));
+    let center = match (found.as_slice(), not_found.is_empty()) {
+      (&[index], true) => Self::resolve(graph.as_ref(), index),
+      _ => None,
+    };
+
+    let filter = PageFilter::new(query.enable_date_related, query.enable_list_article);
+
+    Neighborhood { graph, query, filter, center, cancellation: None }
+  }
+
+  /// Stops expanding once `cancellation` is cancelled.
+  pub fn with_cancellation(mut self, cancellation: CancellationToken) -> Self {
+    self.cancellation = Some(cancellation);
+    self
+  }
+
+  pub fn exec(&self) -> NeighborhoodResult {
+    let start_time = std::time::Instant::now();
+
+    let Some(center) = self.center else {
+      return NeighborhoodResult {
+        not_found: true,
+        center: None,
+        nodes: Vec::new(),
+        edges: Vec::new(),
+        discovered_nodes: 0,
+        sampled: false,
+        duration: start_time.elapsed(),
+      };
+    };
+
+    let depth = self.query.depth.unwrap_or(NeighborhoodQuery::DEFAULT_DEPTH).min(NeighborhoodQuery::MAX_DEPTH);
+    let limit = self.query.limit.unwrap_or(NeighborhoodQuery::DEFAULT_LIMIT).clamp(1, NeighborhoodQuery::MAX_LIMIT);
+    let direction = self.query.direction.unwrap_or_default();
+    let mut rng = StdRng::seed_from_u64(self.query.seed.unwrap_or(0));
+
+    // Sampled-out pages stay seen so they do not come back a hop later.
+    let mut seen = HashSet::from([center]);
+    let mut distances: HashMap<usize, u32> = HashMap::from([(center, 0)]);
+    let mut order = vec![center];
+    let mut discovered_nodes = 1;
+    let mut sampled = false;
+    let mut frontier = vec![center];
+    for distance in 1..=depth {
+      if self.cancellation.as_ref().is_some_and(|cancellation| cancellation.is_cancelled()) {
+        break;
+      }
+      let mut level = Vec::new();
+      for &node_index in &frontier {
+        for neighbor_index in self.neighbors(node_index, direction) {
+          if self.filter.allows(self.graph.as_ref(), neighbor_index) && seen.insert(neighbor_index) {
+            level.push(neighbor_index);
+          }
+        }
+      }
+      discovered_nodes += level.len();
+
+      let room = limit - order.len();
+      if level.len() > room {
+        sampled = true;
+        match self.query.sampling.unwrap_or_default() {
+          Sampling::Degree => level.sort_by_key(|&index| {
+            (std::cmp::Reverse(self.graph.forward_degree(index) + self.graph.backward_degree(index)), index)
+          }),
+          Sampling::Random => level.shuffle(&mut rng),
+        }
+        level.truncate(room);
+      }
+
+      distances.extend(level.iter().map(|&index| (index, distance)));
+      order.extend(&level);
+      frontier = level;
+      if frontier.is_empty() || order.len() >= limit {
+        break;
+      }
+    }
+
+    let mut edges = HashSet::new();
+    for &node_index in &order {
+      for neighbor_index in self.neighbors(node_index, Direction::Forward) {
+        if distances.contains_key(&neighbor_index) {
+          edges.insert((self.graph.id(node_index), self.graph.id(neighbor_index)));
+        }
+      }
+    }
+    let mut edges: Vec<(u32, u32)> = edges.into_iter().collect();
+    edges.sort_unstable();
+
+    NeighborhoodResult {
+      not_found: false,
+      center: Some(GraphSearchResultNode::new(self.graph.as_ref(), center, 0)),
+      nodes: order
+        .iter()
+        .map(|&index| GraphSearchResultNode::new(self.graph.as_ref(), index, distances[&index]))
+        .collect(),
+      edges,
+      discovered_nodes,
+      sampled,
+      duration: start_time.elapsed(),
+    }
+  }
+
+  /// Neighbors of a page with redirects collapsed, possibly repeated.
+  fn neighbors(&self, index: usize, direction: Direction) -> Vec<usize> {
+    let mut neighbors = Vec::new();
+    if direction != Direction::Backward {
+      neighbors.extend(
+        self.graph
+          .forward_neighbors(index)
+          .filter_map(|neighbor_index| Self::resolve(self.graph.as_ref(), neighbor_index)),
+      );
+    }
+    if direction != Direction::Forward {
+      for neighbor_index in self.graph.backward_neighbors(index) {
+        if !self.graph.is_redirect(neighbor_index) {
+          neighbors.push(neighbor_index);
+        } else if self.graph.redirect_target(neighbor_index) == Some(index) {
+          neighbors.extend(
+            self.graph
+              .backward_neighbors(neighbor_index)
+              .filter(|&source_index| !self.graph.is_redirect(source_index)),
+          );
+        }
+      }
+    }
+    neighbors.retain(|&neighbor_index| neighbor_index != index);
+    neighbors
+  }
+
+  /// The page a link to `index` leads to, `None` for a broken or double
+  /// redirect.
+  fn resolve(graph: &G, index: usize) -> Option<usize> {
+    Some(resolve_redirect(graph, index)).filter(|&index| !graph.is_redirect(index))
+  }
+}
@@ -0,0 +1,232 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use rand::{Rng, SeedableRng, rngs::StdRng};
+use serde::{Deserialize, Serialize};
+use crate::graph::GraphView;
+use crate::graph::{CancellationToken, SearchWorkspace};
+use crate::graph::{GraphSearch, GraphSearchQuery, GraphSearchResultNode};
+
+/// Random start and end pages a given number of clicks apart, for the
+/// Wikipedia game.
+///
+/// Both pages are regular articles: no redirects, date-related pages or
+/// lists. Each attempt picks a random start, maps everything it reaches
+/// with `GraphSearch` and picks a random end at the wanted distance, so the
+/// same seed always gives the same pairs on the same graph. Attempts stop
+/// at the request's timeout, and an attempt cut short yields no pair.
+pub struct RandomPairs<G: GraphView = crate::graph::Graph> {
+  graph: Arc<G>,
+  title_to_index: Arc<HashMap<String, usize>>,
+  id_to_index: Arc<HashMap<u32, usize>>,
+  query: RandomPairsQuery,
+  workspace: Option<SearchWorkspace>,
+  cancellation: Option<CancellationToken>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub struct RandomPairsQuery {
+  /// Shortest distance between the start and the end.
+  pub distance: u32,
+  /// Accept any pair at least `distance` apart instead of exactly.
+  pub at_least: Option<bool>,
+  /// Fewest links to a page for it to be picked, to keep to pages players
+  /// have heard of.
+  pub min_in_degree: Option<usize>,
+  /// Random when missing; the one used is returned with the result.
+  pub seed: Option<u64>,
+  /// Number of pairs to generate.
+  pub count: Option<usize>,
+  /// Stops after this many milliseconds, returning the pairs found so far;
+  /// `RandomPairsQuery::DEFAULT_TIMEOUT_MS` when missing.
+  pub timeout_ms: Option<u64>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct RandomPairsResult {
+  pub seed: u64,
+  pub pairs: Vec<RandomPair>,
+  /// Starts tried, at most `RandomPairsQuery::ATTEMPTS_PER_PAIR` per pair.
+  pub attempts: usize,
+  /// No page passes the filters, so nothing could be picked.
+  pub no_candidates: bool,
+  /// Set when the timeout stopped the attempts before `count` pairs were
+  /// found.
+  pub timed_out: bool,
+  pub duration: std::time::Duration,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct RandomPair {
+  pub start: GraphSearchResultNode,
+  pub end: GraphSearchResultNode,
+  pub distance: u32,
+}
+
+impl RandomPairsQuery {
+  pub const DEFAULT_COUNT: usize = 1;
+  pub const MAX_COUNT: usize = 100;
+  pub const ATTEMPTS_PER_PAIR: usize = 10;
+  pub const DEFAULT_TIMEOUT_MS: u64 = 10_000;
+  pub const MAX_TIMEOUT_MS: u64 = 60_000;
+}
+
+impl<G: GraphView> RandomPairs<G> {
+  pub fn with_workspace(
+    graph: Arc<G>,
+    title_to_index: Arc<HashMap<String, usize>>,
+    id_to_index: Arc<HashMap<u32, usize>>,
+    query: RandomPairsQuery,
+    workspace: SearchWorkspace,
+  ) -> Self {
+    RandomPairs { graph, title_to_index, id_to_index, query, workspace: Some(workspace), cancellation: None }
+  }
+
+  /// Stops between attempts, and within one, once `cancellation` is
+  /// cancelled.
+  pub fn with_cancellation(mut self, cancellation: CancellationToken) -> Self {
+    self.cancellation = Some(cancellation);
+    self
+  }
+
+  pub fn exec(&mut self) -> RandomPairsResult {
+    let start_time = std::time::Instant::now();
+    let seed = self.query.seed.unwrap_or_else(rand::random);
+    let mut rng = StdRng::seed_from_u64(seed);
+    let count = self.query.count.unwrap_or(RandomPairsQuery::DEFAULT_COUNT).min(RandomPairsQuery::MAX_COUNT);
+    let timeout = self.query.timeout_ms.unwrap_or(RandomPairsQuery::DEFAULT_TIMEOUT_MS).min(RandomPairsQuery::MAX_TIMEOUT_MS);
+    let deadline = start_time + std::time::Duration::from_millis(timeout);
+
+    let candidates: Vec<usize> = (0..self.graph.node_count()).filter(|&index| self.is_candidate(index)).collect();
+    let mut pairs = Vec::new();
+    let mut attempts = 0;
+    let mut timed_out = false;
+    while !candidates.is_empty() && pairs.len() < count && attempts < count * RandomPairsQuery::ATTEMPTS_PER_PAIR {
+      if self.cancellation.as_ref().is_some_and(CancellationToken::is_cancelled) {
+        break;
+      }
+      let Some(remaining) = deadline.checked_duration_since(std::time::Instant::now()) else {
+        timed_out = true;
+        break;
+      };
+      attempts += 1;
+      let start = candidates[rng.random_range(0..candidates.len())];
+      if let Some(pair) = self.pick_end(start, remaining, &mut rng) {
+        pairs.push(pair);
+      }
+    }
+
+    RandomPairsResult {
+      seed,
+      pairs,
+      attempts,
+      no_candidates: candidates.is_empty(),
+      timed_out,
+      duration: start_time.elapsed(),
+    }
+  }
+
+  /// Hands the workspace back, e.g. to return it to its pool.
+  pub fn into_workspace(self) -> SearchWorkspace {
+    self.workspace.unwrap()
+  }
+
+  /// Maps every page `start` reaches within `timeout` and picks one at
+  /// the wanted distance.
+  fn pick_end(&mut self, start: usize, timeout: std::time::Duration, rng: &mut StdRng) -> Option<RandomPair> {
+    let query = GraphSearchQuery {
+      enable_date_related: Some(false),
+      enable_list_article: Some(false),
+      timeout_ms: Some(timeout.as_millis() as u64),
+      ..Default::default()
+    };
+    let mut graph_search = GraphSearch::with_workspace(
+      self.graph.clone(),
+      self.title_to_index.clone(),
+      self.id_to_index.clone(),
+      query,
+      self.workspace.take().unwrap(),
+    )
+    .with_starts(vec![start]);
+    if let Some(cancellation) = &self.cancellation {
+      graph_search = graph_search.with_cancellation(cancellation.clone());
+    }
+
+    let at_least = self.query.at_least.unwrap_or(false);
+    let (front_distance_map, _, _) = graph_search.map_reachable();
+    // Parallel rounds fill `visited()` in no fixed order, so the ends are
+    // sorted before the pick to keep a seed reproducible.
+    let mut ends: Vec<(usize, u32)> = front_distance_map
+      .visited()
+      .iter()
+      .map(|&index| (index, front_distance_map.get(index)))
+      .filter(|&(index, distance)| {
+        let wanted = if at_least { distance >= self.query.distance } else { distance == self.query.distance };
+        wanted && index != start && self.is_candidate(index)
+      })
+      .collect();
+    ends.sort_unstable_by_key(|&(index, distance)| (distance, index));
+    // A map cut short may miss ends, or have them at the wrong distance.
+    let complete = graph_search.budget_exhausted().is_none();
+
+    let mut workspace = graph_search.into_workspace();
+    workspace.clear();
+    self.workspace = Some(workspace);
+
+    if !complete {
+      return None;
+    }
+    let &(end, distance) = ends.get(rng.random_range(0..ends.len().max(1)))?;
+    Some(RandomPair {
+      start: GraphSearchResultNode::new(self.graph.as_ref(), start, 0),
+      end: GraphSearchResultNode::new(self.graph.as_ref(), end, distance),
+      distance,
+    })
+  }
+
+  fn is_candidate(&self, index: usize) -> bool {
+    !self.graph.is_redirect(index)
+      && !self.graph.is_date_related(index)
+      && !self.graph.is_list_article(index)
+      && self.graph.backward_degree(index) >= self.query.min_in_degree.unwrap_or(0)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::graph::test_graph::TestGraph;
+
+  fn random_pairs(graph: &TestGraph, query: RandomPairsQuery) -> RandomPairsResult {
+    let workspace = SearchWorkspace::new(graph.graph.node_count());
+    RandomPairs::with_workspace(graph.graph.clone(), graph.title_to_index.clone(), graph.id_to_index.clone(), query, workspace)
+      .exec()
+  }
+
+  /// A chain of pages whose titles would not survive a search query.
+  fn chain() -> TestGraph {
+    TestGraph::new(
+      &[("A|B", false), ("#2", false), ("C", false), ("D", false)],
+      &[vec![1], vec![2], vec![3], vec![]],
+    )
+  }
+
+  #[test]
+  fn starts_are_searched_by_index() {
+    let query = RandomPairsQuery { distance: 3, seed: Some(1), count: Some(1), ..Default::default() };
+    let result = random_pairs(&chain(), query);
+    assert!(!result.timed_out);
+    let pairs: Vec<(&str, &str, u32)> = result.pairs
+      .iter()
+      .map(|pair| (pair.start.title.as_str(), pair.end.title.as_str(), pair.distance))
+      .collect();
+    assert_eq!(pairs, vec![("A|B", "D", 3)]);
+  }
+
+  #[test]
+  fn timeout_stops_the_attempts() {
+    let query = RandomPairsQuery { distance: 1, count: Some(3), timeout_ms: Some(0), ..Default::default() };
+    let result = random_pairs(&chain(), query);
+    assert!(result.timed_out);
+    assert!(result.pairs.is_empty());
+  }
+}
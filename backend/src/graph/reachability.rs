@@ -0,0 +1,166 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use rand::{Rng, SeedableRng, rngs::StdRng};
+use serde::{Deserialize, Serialize};
+use crate::graph::GraphView;
+use crate::graph::DistanceMap;
+use crate::graph::{CancellationToken, SearchWorkspace};
+use crate::graph::{GraphSearch, GraphSearchQuery, GraphSearchResultNode};
+use crate::graph::graph_search::PageFilter;
+
+/// How far every page is from one page, following links forward (pages it
+/// leads to) and backward (pages leading to it).
+///
+/// Only non-redirect pages that pass the filters are counted; redirects are
+/// crossed for free as in `GraphSearch`.
+pub struct Reachability<G: GraphView = crate::graph::Graph> {
+  graph: Arc<G>,
+  query: ReachabilityQuery,
+  graph_search: GraphSearch<G>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Default, PartialEq, Eq, Hash)]
+pub struct ReachabilityQuery {
+  pub title: String,
+  pub enable_date_related: Option<bool>,
+  pub enable_list_article: Option<bool>,
+  /// Number of example unreachable pages per direction.
+  pub sample: Option<usize>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ReachabilityResult {
+  pub not_found: bool,
+  pub node: Option<GraphSearchResultNode>,
+  /// Pages reachable from `node`.
+  pub forward: DistanceHistogram,
+  /// Pages that can reach `node`.
+  pub backward: DistanceHistogram,
+  pub distance_overflow: bool,
+  pub duration: std::time::Duration,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+pub struct DistanceHistogram {
+  /// `counts[d]` is the number of pages at distance `d`.
+  pub counts: Vec<usize>,
+  pub reachable: usize,
+  /// Largest distance, `None` when nothing is reachable.
+  pub eccentricity: Option<u32>,
+  pub unreachable: usize,
+  /// Uniformly sampled unreachable pages, the same on every call.
+  pub unreachable_samples: Vec<GraphSearchResultNode>,
+}
+
+impl ReachabilityQuery {
+  pub const DEFAULT_SAMPLE: usize = 20;
+  pub const MAX_SAMPLE: usize = 1000;
+}
+
+impl<G: GraphView> Reachability<G> {
+  pub fn with_workspace(
+    graph: Arc<G>,
+    title_to_index: Arc<HashMap<String, usize>>,
+    id_to_index: Arc<HashMap<u32, usize>>,
+    query: ReachabilityQuery,
+    workspace: SearchWorkspace,
+  ) -> Self {
+    let search_query = GraphSearchQuery {
+      start: query.title.clone(),
+      end: query.title.clone(),
+      enable_date_related: query.enable_date_related,
+      enable_list_article: query.enable_list_article,
+      ..Default::default()
+    };
+    let graph_search = GraphSearch::with_workspace(graph.clone(), title_to_index, id_to_index, search_query, workspace);
+
+    Reachability { graph, query, graph_search }
+  }
+
+  /// Stops walking the graph once `cancellation` is cancelled, leaving the
+  /// histograms incomplete.
+  pub fn with_cancellation(mut self, cancellation: CancellationToken) -> Self {
+    self.graph_search = self.graph_search.with_cancellation(cancellation);
+    self
+  }
+
+  pub fn exec(&mut self) -> ReachabilityResult {
+    let start_time = std::time::Instant::now();
+
+    let index = self.graph_search.starts().first().copied();
+    let Some(index) = index else {
+      return ReachabilityResult {
+        not_found: true,
+        node: None,
+        forward: DistanceHistogram::default(),
+        backward: DistanceHistogram::default(),
+        distance_overflow: false,
+        duration: start_time.elapsed(),
+      };
+    };
+
+    let sample = self.query.sample.unwrap_or(ReachabilityQuery::DEFAULT_SAMPLE).min(ReachabilityQuery::MAX_SAMPLE);
+    let graph = self.graph.clone();
+    let filter = PageFilter::new(self.query.enable_date_related, self.query.enable_list_article);
+    let (front_distance_map, back_distance_map, distance_overflow) = self.graph_search.map_reachable();
+
+    ReachabilityResult {
+      not_found: false,
+      node: Some(GraphSearchResultNode::new(graph.as_ref(), index, 0)),
+      forward: histogram(graph.as_ref(), &filter, front_distance_map, sample),
+      backward: histogram(graph.as_ref(), &filter, back_distance_map, sample),
+      distance_overflow,
+      duration: start_time.elapsed(),
+    }
+  }
+
+  /// Hands the workspace back, e.g. to return it to its pool.
+  pub fn into_workspace(self) -> SearchWorkspace {
+    self.graph_search.into_workspace()
+  }
+}
+
+fn histogram<G: GraphView>(
+  graph: &G,
+  filter: &PageFilter,
+  distance_map: &DistanceMap,
+  sample: usize,
+) -> DistanceHistogram {
+  let mut histogram = DistanceHistogram::default();
+  for &index in distance_map.visited() {
+    if graph.is_redirect(index) {
+      continue;
+    }
+    let distance = distance_map.get(index) as usize;
+    if histogram.counts.len() <= distance {
+      histogram.counts.resize(distance + 1, 0);
+    }
+    histogram.counts[distance] += 1;
+    histogram.reachable += 1;
+  }
+  histogram.eccentricity = histogram.counts.len().checked_sub(1).map(|d| d as u32);
+
+  // Reservoir sampling with a fixed seed keeps the examples stable.
+  let mut rng = StdRng::seed_from_u64(0);
+  let mut samples = Vec::new();
+  for index in 0..graph.node_count() {
+    if distance_map.is_visited(index) || graph.is_redirect(index) || !filter.allows(graph, index) {
+      continue;
+    }
+    histogram.unreachable += 1;
+    if samples.len() < sample {
+      samples.push(index);
+    } else {
+      let slot = rng.random_range(0..histogram.unreachable);
+      if slot < sample {
+        samples[slot] = index;
+      }
+    }
+  }
+  samples.sort_unstable();
+  histogram.unreachable_samples = samples
+    .into_iter()
+    .map(|index| GraphSearchResultNode::new(graph, index, crate::graph::UNVISITED))
+    .collect();
+  histogram
+}
@@ -0,0 +1,146 @@
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use crate::graph::DistanceMap;
+use crate::graph::atomic_bitmap::AtomicBitmap;
+
+/// The per-node buffers a `GraphSearch` needs. Allocating them costs O(N)
+/// for every search, so they are kept in a pool and reset sparsely, making
+/// the per-query cost proportional to the region the search visited.
+pub struct SearchWorkspace {
+  pub(crate) front_distance_map: DistanceMap,
+  pub(crate) back_distance_map: DistanceMap,
+  pub(crate) distance_map: DistanceMap,
+  /// Used as a set: nodes already emitted into the result.
+  pub(crate) visited: DistanceMap,
+  pub(crate) front_queue: VecDeque<usize>,
+  pub(crate) back_queue: VecDeque<usize>,
+  /// Nodes claimed during a parallel expansion; every claim is released
+  /// before the expansion returns.
+  pub(crate) claimed: AtomicBitmap,
+}
+
+impl SearchWorkspace {
+  pub fn new(node_count: usize) -> Self {
+    SearchWorkspace {
+      front_distance_map: DistanceMap::new(node_count),
+      back_distance_map: DistanceMap::new(node_count),
+      distance_map: DistanceMap::new(node_count),
+      visited: DistanceMap::new(node_count),
+      front_queue: VecDeque::new(),
+      back_queue: VecDeque::new(),
+      claimed: AtomicBitmap::new(node_count),
+    }
+  }
+
+  pub fn node_count(&self) -> usize {
+    self.front_distance_map.len()
+  }
+
+  pub fn clear(&mut self) {
+    self.front_distance_map.clear();
+    self.back_distance_map.clear();
+    self.distance_map.clear();
+    self.visited.clear();
+    self.front_queue.clear();
+    self.back_queue.clear();
+  }
+}
+
+/// Idle `SearchWorkspace`s for one graph, shared between requests.
+pub struct SearchWorkspacePool {
+  node_count: usize,
+  max_idle: usize,
+  idle: Mutex<Vec<SearchWorkspace>>,
+}
+
+impl SearchWorkspacePool {
+  /// At most `max_idle` workspaces are kept; more are allocated on demand
+  /// under load and dropped when returned.
+  pub fn new(node_count: usize, max_idle: usize) -> Self {
+    SearchWorkspacePool {
+      node_count,
+      max_idle,
+      idle: Mutex::new(Vec::new()),
+    }
+  }
+
+  pub fn take(&self) -> SearchWorkspace {
+    let workspace = self.idle.lock().unwrap().pop();
+    workspace.unwrap_or_else(|| SearchWorkspace::new(self.node_count))
+  }
+
+  pub fn put(&self, mut workspace: SearchWorkspace) {
+    if workspace.node_count() != self.node_count {
+      return;
+    }
+    workspace.clear();
+    let mut idle = self.idle.lock().unwrap();
+    if idle.len() < self.max_idle {
+      idle.push(workspace);
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn returned_workspaces_come_back_cleared() {
+    let pool = SearchWorkspacePool::new(4, 1);
+    let mut workspace = pool.take();
+    workspace.front_distance_map.set(2, 1);
+    workspace.visited.set(3, 0);
+    workspace.front_queue.push_back(2);
+    pool.put(workspace);
+
+    let workspace = pool.take();
+    assert!(workspace.front_distance_map.visited().is_empty() && !workspace.front_distance_map.is_visited(2));
+    assert!(!workspace.visited.is_visited(3));
+    assert!(workspace.front_queue.is_empty());
+  }
+
+  #[test]
+  fn pool_keeps_at_most_max_idle() {
+    let pool = SearchWorkspacePool::new(4, 1);
+    let (first, second) = (pool.take(), pool.take());
+    pool.put(first);
+    pool.put(second);
+    assert_eq!(pool.idle.lock().unwrap().len(), 1);
+
+    // Workspaces for another graph are not kept.
+    pool.take();
+    pool.put(SearchWorkspace::new(5));
+    assert!(pool.idle.lock().unwrap().is_empty());
+    assert_eq!(pool.take().node_count(), 4);
+  }
+
+  #[test]
+  fn reused_workspace_searches_like_a_fresh_one() {
+    use crate::graph::GraphSearchQuery;
+    use crate::graph::test_graph::TestGraph;
+
+    let graph = TestGraph::new(
+      &[("A", false), ("B", false), ("C", false), ("D", false)],
+      &[vec![1], vec![2], vec![3], vec![0]],
+    );
+    let pool = SearchWorkspacePool::new(4, 1);
+    let search = |start: &str, end: &str| {
+      let query = GraphSearchQuery { start: start.to_string(), end: end.to_string(), ..Default::default() };
+      let mut graph_search = crate::graph::GraphSearch::with_workspace(
+        graph.graph.clone(),
+        graph.title_to_index.clone(),
+        graph.id_to_index.clone(),
+        query,
+        pool.take(),
+      );
+      let result = graph_search.exec();
+      pool.put(graph_search.into_workspace());
+      result.nodes.iter().map(|node| (node.title.clone(), node.distance)).collect::<Vec<_>>()
+    };
+
+    let first = search("B", "A");
+    assert_eq!(search("A", "D"), vec![("A".to_string(), 0), ("B".to_string(), 1), ("C".to_string(), 2), ("D".to_string(), 3)]);
+    assert_eq!(search("B", "A"), first);
+  }
+}
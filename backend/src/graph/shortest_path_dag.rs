@@ -0,0 +1,411 @@
+use std::collections::{HashMap, VecDeque};
+use serde::{Deserialize, Serialize};
+use crate::graph::{GraphSearchResult, GraphSearchResultNode};
+
+/// The shortest-path DAG of a `GraphSearchResult`, indexed for counting and
+/// enumerating the concrete paths it contains.
+///
+/// Children are ordered by title and then page id, so the n-th path is the
+/// same on every call for the same result. Path counts saturate at
+/// `u64::MAX`.
+pub struct ShortestPathDag<'a> {
+  nodes: Vec<&'a GraphSearchResultNode>,
+  children: Vec<Vec<usize>>,
+  parents: Vec<Vec<usize>>,
+  start: usize,
+  end: usize,
+  /// The requested end, which differs from `end` when it is a redirect.
+  end_node: usize,
+  /// Number of paths from each node to `end`.
+  paths_to_end: Vec<u64>,
+  /// Number of paths from `start` to each node.
+  paths_from_start: Vec<u64>,
+}
+
+impl<'a> ShortestPathDag<'a> {
+  /// `None` when the result has no route.
+  pub fn new(result: &'a GraphSearchResult) -> Option<Self> {
+    let start_node = result.start_node.as_ref()?;
+    let end_node = result.end_node.as_ref()?;
+    if !result.route_found || result.nodes.is_empty() {
+      return None;
+    }
+
+    let nodes: Vec<&GraphSearchResultNode> = result.nodes.iter().collect();
+    let id_to_index: HashMap<u32, usize> = nodes
+      .iter()
+      .enumerate()
+      .map(|(i, node)| (node.id, i))
+      .collect();
+
+    let mut children = vec![Vec::new(); nodes.len()];
+    let mut parents = vec![Vec::new(); nodes.len()];
+    for (source, target) in &result.edges {
+      let (Some(&s), Some(&t)) = (id_to_index.get(source), id_to_index.get(target)) else {
+        continue;
+      };
+      children[s].push(t);
+      parents[t].push(s);
+    }
+    for list in children.iter_mut().chain(parents.iter_mut()) {
+      list.sort_by(|&a, &b| nodes[a].title.cmp(&nodes[b].title).then(nodes[a].id.cmp(&nodes[b].id)));
+      list.dedup();
+    }
+
+    let start = *id_to_index.get(&start_node.id)?;
+    let end_node = *id_to_index.get(&end_node.id)?;
+    let mut end = end_node;
+    // A redirected end is reported with an edge to its target, which is
+    // where the paths actually arrive.
+    if nodes[end_node].is_redirect {
+      if let Some(&target) = children[end].first() {
+        end = target;
+      }
+    }
+
+    let order = topological_order(&children);
+    let mut paths_to_end = vec![0u64; nodes.len()];
+    paths_to_end[end] = 1;
+    for &node in order.iter().rev() {
+      if node == end {
+        continue;
+      }
+      paths_to_end[node] = children[node]
+        .iter()
+        .fold(0u64, |count, &child| count.saturating_add(paths_to_end[child]));
+    }
+    let mut paths_from_start = vec![0u64; nodes.len()];
+    paths_from_start[start] = 1;
+    for &node in order.iter() {
+      if node == start {
+        continue;
+      }
+      paths_from_start[node] = parents[node]
+        .iter()
+        .fold(0u64, |count, &parent| count.saturating_add(paths_from_start[parent]));
+    }
+
+    Some(ShortestPathDag { nodes, children, parents, start, end, end_node, paths_to_end, paths_from_start })
+  }
+
+  pub fn path_count(&self) -> u64 {
+    self.paths_to_end[self.start]
+  }
+
+  /// Whether `path_count` hit `u64::MAX` and is only a lower bound.
+  pub fn is_path_count_saturated(&self) -> bool {
+    self.path_count() == u64::MAX
+  }
+
+  pub fn node_count(&self) -> usize {
+    self.nodes.len()
+  }
+
+  pub fn node(&self, index: usize) -> &'a GraphSearchResultNode {
+    self.nodes[index]
+  }
+
+  /// Number of start-to-end paths through a node, saturating.
+  pub fn paths_through(&self, index: usize) -> u64 {
+    self.paths_from_start[index].saturating_mul(self.paths_to_end[index])
+  }
+
+  /// Picks at most `max_nodes` nodes, preferring higher `score`s (ties
+  /// broken by title), while always keeping the endpoints. Each picked node
+  /// comes with its best-scoring chain of parents and children back to
+  /// already picked nodes, so everything kept lies on a start-to-end path;
+  /// candidates whose chain does not fit are skipped.
+  pub fn select(&self, max_nodes: usize, score: impl Fn(usize) -> u64) -> Vec<bool> {
+    let mut keep = vec![false; self.nodes.len()];
+    let mut pinned = vec![self.start, self.end, self.end_node];
+    if self.nodes[self.start].is_redirect {
+      pinned.extend(self.children[self.start].iter().copied());
+    }
+    for &i in &pinned {
+      keep[i] = true;
+    }
+
+    let mut candidates: Vec<usize> = (0..self.nodes.len())
+      .filter(|&i| !keep[i] && self.paths_through(i) > 0)
+      .collect();
+    candidates.sort_by(|&a, &b| {
+      score(b).cmp(&score(a))
+        .then(self.nodes[a].title.cmp(&self.nodes[b].title))
+        .then(self.nodes[a].id.cmp(&self.nodes[b].id))
+    });
+    let mut budget = max_nodes.saturating_sub(keep.iter().filter(|&&k| k).count());
+    for &candidate in &candidates {
+      if budget == 0 {
+        break;
+      }
+      if keep[candidate] {
+        continue;
+      }
+      let mut chain = vec![candidate];
+      self.extend_chain(&mut chain, &keep, &score, |i| &self.parents[i]);
+      self.extend_chain(&mut chain, &keep, &score, |i| &self.children[i]);
+      if chain.len() <= budget {
+        budget -= chain.len();
+        for i in chain {
+          keep[i] = true;
+        }
+      }
+    }
+    keep
+  }
+
+  /// The paths ranked `offset..offset + limit`, as node indices.
+  pub fn paths(&self, offset: u64, limit: usize) -> Vec<Vec<usize>> {
+    let mut paths = Vec::new();
+    let mut rank = offset;
+    while paths.len() < limit && rank < self.path_count() {
+      paths.push(self.path(rank));
+      rank += 1;
+    }
+    paths
+  }
+
+  /// The path with the given rank, found by skipping whole subtrees using
+  /// the per-node path counts.
+  fn path(&self, mut rank: u64) -> Vec<usize> {
+    let mut node = self.start;
+    let mut path = vec![node];
+    while node != self.end {
+      let mut next = None;
+      for &child in &self.children[node] {
+        if rank < self.paths_to_end[child] {
+          next = Some(child);
+          break;
+        }
+        rank -= self.paths_to_end[child];
+      }
+      match next {
+        Some(child) => {
+          node = child;
+          path.push(node);
+        },
+        None => break,
+      }
+    }
+    path
+  }
+}
+
+impl ShortestPathDag<'_> {
+  /// Follows the best-scoring neighbors from the last node of `chain` until
+  /// reaching a kept node, appending the nodes passed on the way.
+  fn extend_chain<'b>(
+    &'b self,
+    chain: &mut Vec<usize>,
+    keep: &[bool],
+    score: &impl Fn(usize) -> u64,
+    next: impl Fn(usize) -> &'b Vec<usize>,
+  ) {
+    let mut node = chain[0];
+    loop {
+      let neighbors = next(node).iter().copied().filter(|&i| self.paths_through(i) > 0);
+      if neighbors.clone().any(|i| keep[i]) {
+        return;
+      }
+      let best = neighbors.max_by(|&a, &b| {
+        score(a).cmp(&score(b)).then(self.nodes[b].title.cmp(&self.nodes[a].title))
+      });
+      match best {
+        Some(neighbor) if !chain.contains(&neighbor) => {
+          chain.push(neighbor);
+          node = neighbor;
+        },
+        _ => return,
+      }
+    }
+  }
+}
+
+fn topological_order(children: &[Vec<usize>]) -> Vec<usize> {
+  let mut in_degrees = vec![0usize; children.len()];
+  for &child in children.iter().flatten() {
+    in_degrees[child] += 1;
+  }
+  let mut queue: VecDeque<usize> = (0..children.len()).filter(|&i| in_degrees[i] == 0).collect();
+  let mut order = Vec::with_capacity(children.len());
+  while let Some(node) = queue.pop_front() {
+    order.push(node);
+    for &child in &children[node] {
+      in_degrees[child] -= 1;
+      if in_degrees[child] == 0 {
+        queue.push_back(child);
+      }
+    }
+  }
+  order
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ShortestPathsQuery {
+  pub limit: Option<usize>,
+  /// Opaque position returned as `next_cursor` by the previous page.
+  pub cursor: Option<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ShortestPathsResult {
+  pub start_not_found: bool,
+  pub end_not_found: bool,
+  pub route_found: bool,
+  pub distance: Option<u32>,
+  pub path_count: u64,
+  pub path_count_saturated: bool,
+  pub paths: Vec<Vec<GraphSearchResultNode>>,
+  pub next_cursor: Option<String>,
+  pub duration: std::time::Duration,
+}
+
+impl ShortestPathsResult {
+  pub const DEFAULT_LIMIT: usize = 10;
+  pub const MAX_LIMIT: usize = 100;
+
+  pub fn new(result: &GraphSearchResult, query: &ShortestPathsQuery) -> Result<Self, String> {
+    let offset: u64 = match &query.cursor {
+      Some(cursor) => cursor.parse().map_err(|_| format!("invalid cursor: {}", cursor))?,
+      None => 0,
+    };
+    let limit = query.limit.unwrap_or(Self::DEFAULT_LIMIT).min(Self::MAX_LIMIT);
+
+    let dag = ShortestPathDag::new(result);
+    let (path_count, path_count_saturated, paths) = match &dag {
+      Some(dag) => (
+        dag.path_count(),
+        dag.is_path_count_saturated(),
+        dag.paths(offset, limit)
+          .into_iter()
+          .map(|path| path.into_iter().map(|index| dag.node(index).clone()).collect())
+          .collect(),
+      ),
+      None => (0, false, Vec::new()),
+    };
+    let next_offset = offset.saturating_add(paths.len() as u64);
+    let next_cursor = if next_offset < path_count && !paths.is_empty() {
+      Some(next_offset.to_string())
+    } else {
+      None
+    };
+
+    Ok(ShortestPathsResult {
+      start_not_found: result.start_not_found,
+      end_not_found: result.end_not_found,
+      route_found: result.route_found,
+      distance: result.end_node.as_ref().filter(|_| result.route_found).map(|node| node.distance),
+      path_count,
+      path_count_saturated,
+      paths,
+      next_cursor,
+      duration: result.duration,
+    })
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::graph::{GraphSearchQuery, RankBy};
+  use crate::graph::test_graph::TestGraph;
+
+  /// S -> {C, A, B} -> {Y, X} -> E: six shortest paths.
+  fn grid() -> GraphSearchResult {
+    let graph = TestGraph::new(
+      &[("S", false), ("C", false), ("A", false), ("B", false), ("Y", false), ("X", false), ("E", false)],
+      &[vec![1, 2, 3], vec![4, 5], vec![4, 5], vec![4, 5], vec![6], vec![6], vec![]],
+    );
+    graph.search(GraphSearchQuery { start: "S".to_string(), end: "E".to_string(), ..Default::default() }).exec()
+  }
+
+  fn titles(paths: &[Vec<GraphSearchResultNode>]) -> Vec<String> {
+    paths.iter().map(|path| path.iter().map(|node| node.title.as_str()).collect::<Vec<_>>().join("")).collect()
+  }
+
+  #[test]
+  fn paths_are_counted_and_ordered_by_title() {
+    let result = grid();
+    let dag = ShortestPathDag::new(&result).unwrap();
+    assert_eq!(dag.path_count(), 6);
+    assert!(!dag.is_path_count_saturated());
+    let through = |title: &str| dag.paths_through((0..dag.node_count()).find(|&i| dag.node(i).title == title).unwrap());
+    assert_eq!((through("S"), through("A"), through("X"), through("E")), (6, 2, 3, 6));
+
+    let all = dag.paths(0, 10);
+    let ranked: Vec<String> = all.iter().map(|path| path.iter().map(|&i| dag.node(i).title.as_str()).collect()).collect();
+    assert_eq!(ranked, vec!["SAXE", "SAYE", "SBXE", "SBYE", "SCXE", "SCYE"]);
+    assert_eq!(dag.paths(4, 10), all[4..].to_vec());
+    assert!(dag.paths(6, 10).is_empty());
+  }
+
+  #[test]
+  fn cursors_page_through_every_path_once() {
+    let result = grid();
+    let first = ShortestPathsResult::new(&result, &ShortestPathsQuery { limit: Some(4), cursor: None }).unwrap();
+    assert_eq!((first.path_count, first.distance), (6, Some(3)));
+    assert_eq!(titles(&first.paths), vec!["SAXE", "SAYE", "SBXE", "SBYE"]);
+    assert_eq!(first.next_cursor.as_deref(), Some("4"));
+
+    let second = ShortestPathsResult::new(&result, &ShortestPathsQuery { limit: Some(4), cursor: first.next_cursor }).unwrap();
+    assert_eq!(titles(&second.paths), vec!["SCXE", "SCYE"]);
+    assert_eq!(second.next_cursor, None);
+
+    let cursor = Some("next".to_string());
+    assert!(ShortestPathsResult::new(&result, &ShortestPathsQuery { limit: None, cursor }).is_err());
+  }
+
+  /// S -> A -> {X, Y} -> E and S -> B -> X -> E, where B also links to
+  /// five pages off the route.
+  fn capped(max_nodes: usize, rank_by: RankBy) -> GraphSearchResult {
+    let mut pages = vec![("S", false), ("A", false), ("B", false), ("X", false), ("Y", false), ("E", false)];
+    let mut links = vec![vec![1, 2], vec![3, 4], vec![3], vec![5], vec![5], vec![]];
+    let leaves: Vec<String> = (0..5).map(|index| format!("L{}", index)).collect();
+    for leaf in &leaves {
+      links[2].push(pages.len());
+      pages.push((leaf.as_str(), false));
+      links.push(Vec::new());
+    }
+    let graph = TestGraph::new(&pages, &links);
+    graph.search(GraphSearchQuery {
+      start: "S".to_string(),
+      end: "E".to_string(),
+      max_nodes: Some(max_nodes),
+      rank_by: Some(rank_by),
+      ..Default::default()
+    }).exec()
+  }
+
+  fn node_titles(result: &GraphSearchResult) -> Vec<&str> {
+    let mut titles: Vec<&str> = result.nodes.iter().map(|node| node.title.as_str()).collect();
+    titles.sort_unstable();
+    titles
+  }
+
+  #[test]
+  fn capped_dag_keeps_best_ranked_route() {
+    let result = capped(4, RankBy::Paths);
+    assert!(result.truncated);
+    assert_eq!(node_titles(&result), vec!["A", "E", "S", "X"]);
+    assert_eq!((result.dropped_nodes, result.dropped_paths), (2, 2));
+
+    let result = capped(4, RankBy::HighDegree);
+    assert_eq!(node_titles(&result), vec!["B", "E", "S", "X"]);
+
+    let result = capped(5, RankBy::LowDegree);
+    assert_eq!(node_titles(&result), vec!["A", "E", "S", "X", "Y"]);
+
+    let result = capped(6, RankBy::Paths);
+    assert!(!result.truncated && result.dropped_nodes == 0);
+  }
+
+  #[test]
+  fn select_always_keeps_the_endpoints() {
+    let result = capped(6, RankBy::Paths);
+    let dag = ShortestPathDag::new(&result).unwrap();
+    let keep = dag.select(1, |_| 0);
+    let kept: Vec<&str> = (0..dag.node_count()).filter(|&i| keep[i]).map(|i| dag.node(i).title.as_str()).collect();
+    assert_eq!(kept.len(), 2);
+    assert!(kept.contains(&"S") && kept.contains(&"E"));
+  }
+}
@@ -0,0 +1,109 @@
+use std::sync::Arc;
+use crate::graph::GraphView;
+
+/// A node-induced subgraph of another `GraphView`.
+///
+/// Kept nodes are renumbered densely in their original order, and edges to
+/// dropped nodes disappear from both adjacency directions.
+pub struct Subgraph<G: GraphView = crate::graph::Graph> {
+  graph: Arc<G>,
+  kept: Vec<usize>,
+  original_to_kept: Vec<Option<usize>>,
+}
+
+pub struct SubgraphNeighbors<'a, G: GraphView + 'a> {
+  neighbors: G::Neighbors<'a>,
+  original_to_kept: &'a [Option<usize>],
+}
+
+impl<'a, G: GraphView + 'a> Iterator for SubgraphNeighbors<'a, G> {
+  type Item = usize;
+
+  fn next(&mut self) -> Option<usize> {
+    for original in self.neighbors.by_ref() {
+      if let Some(index) = self.original_to_kept[original] {
+        return Some(index);
+      }
+    }
+    None
+  }
+}
+
+impl<G: GraphView> Subgraph<G> {
+  pub fn new(graph: Arc<G>, keep: impl Fn(&G, usize) -> bool) -> Self {
+    let mut kept = Vec::new();
+    let original_to_kept = (0..graph.node_count())
+      .map(|index| {
+        if keep(graph.as_ref(), index) {
+          kept.push(index);
+          Some(kept.len() - 1)
+        } else {
+          None
+        }
+      })
+      .collect();
+    Subgraph { graph, kept, original_to_kept }
+  }
+
+  /// Index in the underlying graph of a subgraph node.
+  pub fn original_index(&self, index: usize) -> usize {
+    self.kept[index]
+  }
+
+  /// Index in this subgraph of an underlying node, if it was kept.
+  pub fn subgraph_index(&self, original: usize) -> Option<usize> {
+    self.original_to_kept[original]
+  }
+}
+
+impl<G: GraphView> GraphView for Subgraph<G> {
+  type Neighbors<'a> = SubgraphNeighbors<'a, G> where Self: 'a;
+
+  fn node_count(&self) -> usize {
+    self.kept.len()
+  }
+
+  fn id(&self, index: usize) -> u32 {
+    self.graph.id(self.kept[index])
+  }
+
+  fn ns(&self, index: usize) -> i32 {
+    self.graph.ns(self.kept[index])
+  }
+
+  fn title(&self, index: usize) -> &str {
+    self.graph.title(self.kept[index])
+  }
+
+  fn is_redirect(&self, index: usize) -> bool {
+    self.graph.is_redirect(self.kept[index])
+  }
+
+  fn is_date_related(&self, index: usize) -> bool {
+    self.graph.is_date_related(self.kept[index])
+  }
+
+  fn is_list_article(&self, index: usize) -> bool {
+    self.graph.is_list_article(self.kept[index])
+  }
+
+  fn forward_neighbors(&self, index: usize) -> Self::Neighbors<'_> {
+    SubgraphNeighbors {
+      neighbors: self.graph.forward_neighbors(self.kept[index]),
+      original_to_kept: &self.original_to_kept,
+    }
+  }
+
+  fn backward_neighbors(&self, index: usize) -> Self::Neighbors<'_> {
+    SubgraphNeighbors {
+      neighbors: self.graph.backward_neighbors(self.kept[index]),
+      original_to_kept: &self.original_to_kept,
+    }
+  }
+
+  fn redirect_target(&self, index: usize) -> Option<usize> {
+    self.graph
+      .redirect_target(self.kept[index])
+      .and_then(|target| self.original_to_kept[target])
+  }
+}
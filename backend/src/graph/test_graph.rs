@@ -0,0 +1,41 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use crate::graph::{Graph, GraphSearch, GraphSearchQuery, Node};
+
+/// A small graph for unit tests, with the title and id lookups searches
+/// are built with. Pages get ids counting from 1.
+pub(crate) struct TestGraph {
+  pub graph: Arc<Graph>,
+  pub title_to_index: Arc<HashMap<String, usize>>,
+  pub id_to_index: Arc<HashMap<u32, usize>>,
+}
+
+impl TestGraph {
+  /// `pages` holds each page's title and whether it is a redirect, and
+  /// `links[i]` the indices page `i` links to.
+  pub fn new(pages: &[(&str, bool)], links: &[Vec<usize>]) -> Self {
+    let nodes = pages
+      .iter()
+      .enumerate()
+      .map(|(index, &(title, is_redirect))| Node {
+        id: index as u32 + 1,
+        ns: 0,
+        title: title.to_string(),
+        is_redirect,
+        is_date_related: false,
+        is_list_article: false,
+        forward_edge_range: (0, 0),
+        backward_edge_range: (0, 0),
+      })
+      .collect();
+    TestGraph {
+      graph: Arc::new(Graph::from_links(nodes, links)),
+      title_to_index: Arc::new(pages.iter().enumerate().map(|(index, &(title, _))| (title.to_string(), index)).collect()),
+      id_to_index: Arc::new((0..pages.len()).map(|index| (index as u32 + 1, index)).collect()),
+    }
+  }
+
+  pub fn search(&self, query: GraphSearchQuery) -> GraphSearch {
+    GraphSearch::new(self.graph.clone(), self.title_to_index.clone(), self.id_to_index.clone(), query)
+  }
+}
@@ -0,0 +1,520 @@
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, HashSet};
+use std::sync::Arc;
+use serde::{Deserialize, Serialize};
+use crate::graph::GraphView;
+use crate::graph::EdgeAttributes;
+use crate::graph::{Budget, CancellationToken, DistanceOracle, SearchWorkspace};
+use crate::graph::{Direction, GraphSearchQuery, GraphSearchResult, GraphSearchResultNode};
+use crate::graph::graph_search::{resolve_pages, resolve_redirect, PageFilter};
+
+/// Cheapest routes when links have different costs (Dijkstra, or A* with a
+/// `DistanceOracle`).
+///
+/// A link costs `base_cost` plus penalties for links found only in
+/// templates, links far down the page and links to pages with many inbound
+/// links; a link from a redirect costs nothing. Template and position
+/// penalties need `EdgeAttributes`; without them only the hub penalty
+/// applies. Distances in the result are these costs, and `max_depth` bounds
+/// the cost of the route. `max_visited`, `timeout_ms` and cancellation stop
+/// the search like they stop `GraphSearch`. Queries setting
+/// `GraphSearchQuery::unsupported_parameter` are meant to be rejected
+/// before searching.
+pub struct WeightedSearch<G: GraphView = crate::graph::Graph> {
+  graph: Arc<G>,
+  query: GraphSearchQuery,
+  edge_cost: EdgeCost,
+  workspace: SearchWorkspace,
+  starts: Vec<usize>,
+  ends: Vec<usize>,
+  filter: PageFilter,
+  avoid_not_found: Vec<String>,
+  /// Guides the search towards the ends; every link that is not free costs
+  /// at least `base_cost`, so landmark bounds times it never overestimate.
+  oracle: Option<Arc<DistanceOracle>>,
+  cancellation: Option<CancellationToken>,
+  deadline: Option<std::time::Instant>,
+  budget_exhausted: Option<Budget>,
+  budget_checks: u32,
+  /// Set when a route was cut short by `max_depth`.
+  depth_limited: bool,
+  discovered_nodes: u32,
+  visited_nodes: u32,
+  distance_overflow: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub struct EdgeCostQuery {
+  pub base_cost: Option<u32>,
+  /// Added when every occurrence of a link is inside a template.
+  pub template_cost: Option<u32>,
+  /// Added in full for a link first appearing at the very end of the page,
+  /// and proportionally less further up.
+  pub position_cost: Option<u32>,
+  /// Added in proportion to the logarithm of the target's in-degree, in
+  /// full from about a million inbound links.
+  pub hub_cost: Option<u32>,
+}
+
+impl EdgeCostQuery {
+  pub const DEFAULT_BASE_COST: u32 = 10;
+  pub const DEFAULT_TEMPLATE_COST: u32 = 10;
+  pub const DEFAULT_POSITION_COST: u32 = 5;
+  pub const DEFAULT_HUB_COST: u32 = 5;
+}
+
+impl<G: GraphView> WeightedSearch<G> {
+  /// Searches in a cleared workspace, typically taken from a
+  /// `SearchWorkspacePool`.
+  pub fn with_workspace(
+    graph: Arc<G>,
+    edge_attributes: Option<Arc<EdgeAttributes>>,
+    title_to_index: Arc<HashMap<String, usize>>,
+    id_to_index: Arc<HashMap<u32, usize>>,
+    query: GraphSearchQuery,
+    costs: EdgeCostQuery,
+    workspace: SearchWorkspace,
+  ) -> Self {
+    debug_assert_eq!(workspace.node_count(), graph.node_count());
+    let edge_attributes = edge_attributes.filter(|attributes| attributes.matches(graph.as_ref()));
+
+    let (starts, starts_not_found) = resolve_pages(&title_to_index, &id_to_index, Some(&query.start));
+    let starts = if starts_not_found.is_empty() { starts } else { Vec::new() };
+    let (ends, ends_not_found) = resolve_pages(&title_to_index, &id_to_index, Some(&query.end));
+    let ends = if ends_not_found.is_empty() { ends } else { Vec::new() };
+    let (filter, avoid_not_found) = PageFilter::for_query(graph.as_ref(), &title_to_index, &id_to_index, &query);
+
+    WeightedSearch {
+      graph,
+      query,
+      edge_cost: EdgeCost { edge_attributes, costs },
+      workspace,
+      starts,
+      ends,
+      filter,
+      avoid_not_found,
+      oracle: None,
+      cancellation: None,
+      deadline: None,
+      budget_exhausted: None,
+      budget_checks: 0,
+      depth_limited: false,
+      discovered_nodes: 0,
+      visited_nodes: 0,
+      distance_overflow: false,
+    }
+  }
+
+  /// Searches with A*, using `oracle` for lower bounds on the cost left.
+  pub fn with_oracle(mut self, oracle: Option<Arc<DistanceOracle>>) -> Self {
+    self.oracle = oracle.filter(|oracle| oracle.matches(self.graph.as_ref()));
+    self
+  }
+
+  /// Stops the search once `cancellation` is cancelled.
+  pub fn with_cancellation(mut self, cancellation: CancellationToken) -> Self {
+    self.cancellation = Some(cancellation);
+    self
+  }
+
+  pub fn exec(&mut self) -> GraphSearchResult {
+    let start_time = std::time::Instant::now();
+    let mut result = GraphSearchResult {
+      discovered_nodes: 0,
+      visited_nodes: 0,
+      start_node: None,
+      end_node: None,
+      nodes: Vec::new(),
+      edges: Vec::new(),
+      edge_directions: Vec::new(),
+      start_not_found: self.starts.is_empty(),
+      end_not_found: self.ends.is_empty(),
+      route_found: false,
+      is_start_end_some: false,
+      distance_overflow: false,
+      truncated: false,
+      dropped_nodes: 0,
+      dropped_paths: 0,
+      avoid_not_found: self.avoid_not_found.clone(),
+      via_not_found: Vec::new(),
+      budget_exhausted: None,
+      front_frontier: 0,
+      back_frontier: 0,
+      duration: std::time::Duration::ZERO,
+    };
+    if self.starts.is_empty() || self.ends.is_empty() {
+      result.duration = start_time.elapsed();
+      return result;
+    }
+
+    let targets: HashSet<usize> = self.ends.iter().map(|&end| self.seed(end)).collect();
+    if self.starts.iter().any(|&start| targets.contains(&self.seed(start))) {
+      result.route_found = true;
+      result.is_start_end_some = true;
+      result.duration = start_time.elapsed();
+      return result;
+    }
+
+    self.deadline = self.query.timeout_ms.map(|timeout| start_time + std::time::Duration::from_millis(timeout));
+    let best = self.dijkstra(&targets);
+    if best.is_none() && self.budget_exhausted.is_none() && self.depth_limited {
+      self.budget_exhausted = Some(Budget::MaxDepth);
+    }
+    self.collect_dag(&mut result, &targets, best);
+
+    result.discovered_nodes = self.discovered_nodes;
+    result.visited_nodes = self.visited_nodes;
+    result.distance_overflow = self.distance_overflow;
+    result.budget_exhausted = self.budget_exhausted;
+    result.duration = start_time.elapsed();
+    result
+  }
+
+  /// Hands the workspace back, e.g. to return it to its pool.
+  pub fn into_workspace(self) -> SearchWorkspace {
+    self.workspace
+  }
+
+  /// Settles nodes in order of cost plus estimated cost left until the
+  /// cheapest target is settled, along with every other node that may be as
+  /// cheap as it. Returns the target cost.
+  ///
+  /// Landmark bounds are not always consistent, so a node is expanded again
+  /// whenever a cheaper way to it turns up.
+  fn dijkstra(&mut self, targets: &HashSet<usize>) -> Option<u32> {
+    let max_depth = self.query.max_depth.unwrap_or(crate::graph::UNVISITED);
+    let mut heap = BinaryHeap::new();
+    for &start in &self.starts {
+      let seed = resolve_redirect(self.graph.as_ref(), start);
+      let Some(estimate) = self.estimate(seed, targets) else {
+        continue;
+      };
+      self.workspace.front_distance_map.set(seed, 0);
+      heap.push(Reverse((estimate, 0u32, seed)));
+    }
+
+    let mut best = None;
+    while let Some(Reverse((bound, distance, node_index))) = heap.pop() {
+      if best.is_some_and(|best| bound > best) {
+        break;
+      }
+      if self.out_of_budget() {
+        return None;
+      }
+      if distance > self.workspace.front_distance_map.get(node_index) {
+        continue;
+      }
+      if !self.workspace.visited.is_visited(node_index) {
+        self.workspace.visited.set(node_index, 0);
+      }
+      self.visited_nodes += 1;
+      if targets.contains(&node_index) {
+        best.get_or_insert(distance);
+        continue;
+      }
+
+      for (offset, neighbor_index) in self.graph.forward_neighbors(node_index).enumerate() {
+        self.discovered_nodes += 1;
+        if !targets.contains(&neighbor_index) && !self.filter.allows(self.graph.as_ref(), neighbor_index) {
+          continue;
+        }
+        let cost = self.edge_cost.cost(self.graph.as_ref(), node_index, offset, neighbor_index);
+        let Some(neighbor_distance) = distance.checked_add(cost).filter(|&d| d != crate::graph::UNVISITED) else {
+          self.distance_overflow = true;
+          continue;
+        };
+        if neighbor_distance > max_depth {
+          self.depth_limited = true;
+          continue;
+        }
+        if neighbor_distance >= self.workspace.front_distance_map.get(neighbor_index) {
+          continue;
+        }
+        let Some(estimate) = self.estimate(neighbor_index, targets) else {
+          continue;
+        };
+        self.workspace.front_distance_map.set(neighbor_index, neighbor_distance);
+        heap.push(Reverse((neighbor_distance.saturating_add(estimate), neighbor_distance, neighbor_index)));
+      }
+    }
+    best
+  }
+
+  /// A lower bound on the cost from `index` to the nearest target, 0
+  /// without an oracle, or `None` when the oracle knows no target can be
+  /// reached from it.
+  fn estimate(&self, index: usize, targets: &HashSet<usize>) -> Option<u32> {
+    let Some(oracle) = &self.oracle else {
+      return Some(0);
+    };
+    let hops = targets.iter().filter_map(|&target| oracle.lower_bound(index, target)).min()?;
+    Some(hops.saturating_mul(self.edge_cost.min_cost()))
+  }
+
+  /// Whether `max_visited`, the deadline or cancellation stops the search,
+  /// remembering which one did. Like `GraphSearch`, the clock is only read
+  /// every so many checks.
+  fn out_of_budget(&mut self) -> bool {
+    if self.budget_exhausted.is_some() {
+      return true;
+    }
+    self.budget_checks = self.budget_checks.wrapping_add(1);
+    let budget = if self.query.max_visited.is_some_and(|max| self.visited_nodes >= max) {
+      Budget::MaxVisited
+    } else if self.cancellation.as_ref().is_some_and(|cancellation| cancellation.is_cancelled()) {
+      Budget::Cancelled
+    } else if self.deadline.is_some_and(|deadline| self.budget_checks % 256 == 0 && std::time::Instant::now() >= deadline) {
+      Budget::Timeout
+    } else {
+      return false;
+    };
+    self.budget_exhausted = Some(budget);
+    true
+  }
+
+  /// Walks back from the cheapest targets over links whose cost accounts
+  /// exactly for the difference in distance.
+  fn collect_dag(&mut self, result: &mut GraphSearchResult, targets: &HashSet<usize>, best: Option<u32>) {
+    let Some(best) = best else {
+      let start = self.starts[0];
+      let end = self.ends[0];
+      result.start_node = Some(GraphSearchResultNode::new(self.graph.as_ref(), start, crate::graph::UNVISITED));
+      result.end_node = Some(GraphSearchResultNode::new(self.graph.as_ref(), end, crate::graph::UNVISITED));
+      return;
+    };
+
+    let distance_map = &self.workspace.front_distance_map;
+    let settled = &self.workspace.visited;
+    let in_dag = &mut self.workspace.distance_map;
+    let mut queue: Vec<usize> = targets
+      .iter()
+      .copied()
+      .filter(|&target| settled.is_visited(target) && distance_map.get(target) == best)
+      .collect();
+    for &target in &queue {
+      in_dag.set(target, 0);
+    }
+
+    // Links whose cost accounts exactly for the difference in distance, by
+    // the page they lead to. Targets were not expanded.
+    let mut parents: HashMap<usize, Vec<usize>> = HashMap::new();
+    for &parent_index in settled.visited() {
+      if targets.contains(&parent_index) {
+        continue;
+      }
+      let parent_distance = distance_map.get(parent_index);
+      for (offset, node_index) in self.graph.forward_neighbors(parent_index).enumerate() {
+        if node_index == parent_index || !settled.is_visited(node_index) {
+          continue;
+        }
+        let cost = self.edge_cost.cost(self.graph.as_ref(), parent_index, offset, node_index);
+        if parent_distance.checked_add(cost) == Some(distance_map.get(node_index)) {
+          parents.entry(node_index).or_default().push(parent_index);
+        }
+      }
+    }
+
+    let mut edges = Vec::new();
+    while let Some(node_index) = queue.pop() {
+      for &parent_index in parents.get(&node_index).into_iter().flatten() {
+        edges.push((parent_index, node_index));
+        if !in_dag.is_visited(parent_index) {
+          in_dag.set(parent_index, 0);
+          queue.push(parent_index);
+        }
+      }
+    }
+
+    let mut indices = in_dag.visited().to_vec();
+    // Requested redirects hang off the DAG with an edge to their target.
+    for &index in self.starts.iter().chain(&self.ends) {
+      let seed = resolve_redirect(self.graph.as_ref(), index);
+      if seed != index && in_dag.is_visited(seed) && !indices.contains(&index) {
+        indices.push(index);
+        edges.push((index, seed));
+      }
+    }
+    let distance_of = |index: usize| {
+      let seed = self.graph.redirect_target(index).filter(|_| !distance_map.is_visited(index)).unwrap_or(index);
+      distance_map.get(seed)
+    };
+    indices.sort_by_key(|&index| (distance_of(index), index));
+
+    let start = self.starts
+      .iter()
+      .copied()
+      .find(|&start| in_dag.is_visited(resolve_redirect(self.graph.as_ref(), start)))
+      .unwrap_or(self.starts[0]);
+    let end = self.ends
+      .iter()
+      .copied()
+      .find(|&end| in_dag.is_visited(resolve_redirect(self.graph.as_ref(), end)))
+      .unwrap_or(self.ends[0]);
+
+    result.route_found = true;
+    result.start_node = Some(GraphSearchResultNode::new(self.graph.as_ref(), start, 0));
+    result.end_node = Some(GraphSearchResultNode::new(self.graph.as_ref(), end, best));
+    result.nodes = indices
+      .iter()
+      .map(|&index| GraphSearchResultNode::new(self.graph.as_ref(), index, distance_of(index)))
+      .collect();
+    result.edges = edges
+      .iter()
+      .map(|&(source, target)| (self.graph.id(source), self.graph.id(target)))
+      .collect();
+    result.edge_directions = vec![Direction::Forward; edges.len()];
+  }
+
+  fn seed(&self, index: usize) -> usize {
+    resolve_redirect(self.graph.as_ref(), index)
+  }
+}
+
+struct EdgeCost {
+  edge_attributes: Option<Arc<EdgeAttributes>>,
+  costs: EdgeCostQuery,
+}
+
+impl EdgeCost {
+  /// The least a link that is not out of a redirect can cost.
+  fn min_cost(&self) -> u32 {
+    self.costs.base_cost.unwrap_or(EdgeCostQuery::DEFAULT_BASE_COST)
+  }
+
+  /// Cost of the `offset`-th link of `source`, which points to `target`.
+  fn cost<G: GraphView>(&self, graph: &G, source: usize, offset: usize, target: usize) -> u32 {
+    if graph.is_redirect(source) {
+      return 0;
+    }
+    let mut cost = self.costs.base_cost.unwrap_or(EdgeCostQuery::DEFAULT_BASE_COST);
+    if let Some(edge_attributes) = &self.edge_attributes {
+      let attribute = edge_attributes.get(graph, source, offset);
+      if attribute.in_template {
+        cost += self.costs.template_cost.unwrap_or(EdgeCostQuery::DEFAULT_TEMPLATE_COST);
+      }
+      let position_cost = self.costs.position_cost.unwrap_or(EdgeCostQuery::DEFAULT_POSITION_COST);
+      cost += position_cost * attribute.position as u32 / u8::MAX as u32;
+    }
+    let hub_cost = self.costs.hub_cost.unwrap_or(EdgeCostQuery::DEFAULT_HUB_COST) as f64;
+    if hub_cost > 0.0 {
+      let in_degree = graph.backward_degree(target) as f64;
+      cost += (hub_cost * (in_degree + 1.0).log2() / 20.0).min(hub_cost) as u32;
+    }
+    cost
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::graph::test_graph::TestGraph;
+  use crate::graph::LandmarkSelection;
+
+  fn search(graph: &TestGraph, query: GraphSearchQuery, costs: EdgeCostQuery, oracle: Option<Arc<DistanceOracle>>) -> GraphSearchResult {
+    let workspace = SearchWorkspace::new(graph.graph.node_count());
+    WeightedSearch::with_workspace(
+      graph.graph.clone(),
+      None,
+      graph.title_to_index.clone(),
+      graph.id_to_index.clone(),
+      query,
+      costs,
+      workspace,
+    )
+    .with_oracle(oracle)
+    .exec()
+  }
+
+  fn query(start: &str, end: &str) -> GraphSearchQuery {
+    GraphSearchQuery { start: start.to_string(), end: end.to_string(), ..Default::default() }
+  }
+
+  /// A -> H -> E and A -> B -> C -> E, with twenty more pages linking to
+  /// the hub H.
+  fn hub() -> TestGraph {
+    let mut pages = vec![("A", false), ("H", false), ("B", false), ("C", false), ("E", false)];
+    let mut links = vec![vec![1, 2], vec![4], vec![3], vec![4], vec![]];
+    let fans: Vec<String> = (0..20).map(|index| format!("F{}", index)).collect();
+    for fan in &fans {
+      pages.push((fan.as_str(), false));
+      links.push(vec![1]);
+    }
+    TestGraph::new(&pages, &links)
+  }
+
+  fn route(result: &GraphSearchResult) -> Vec<&str> {
+    result.nodes.iter().map(|node| node.title.as_str()).collect()
+  }
+
+  #[test]
+  fn hub_penalty_picks_longer_route() {
+    let graph = hub();
+    let result = search(&graph, query("A", "E"), EdgeCostQuery { hub_cost: Some(0), ..Default::default() }, None);
+    assert_eq!(route(&result), vec!["A", "H", "E"]);
+    assert_eq!(result.end_node.unwrap().distance, 2 * EdgeCostQuery::DEFAULT_BASE_COST);
+
+    let costs = EdgeCostQuery { base_cost: Some(1), hub_cost: Some(100), ..Default::default() };
+    let result = search(&graph, query("A", "E"), costs, None);
+    assert_eq!(route(&result), vec!["A", "B", "C", "E"]);
+  }
+
+  #[test]
+  fn budgets_stop_the_search() {
+    let graph = hub();
+    let costs = EdgeCostQuery { hub_cost: Some(0), ..Default::default() };
+
+    let result = search(&graph, GraphSearchQuery { max_visited: Some(1), ..query("A", "E") }, costs.clone(), None);
+    assert!(!result.route_found);
+    assert_eq!(result.budget_exhausted, Some(Budget::MaxVisited));
+
+    let max_depth = 2 * EdgeCostQuery::DEFAULT_BASE_COST - 1;
+    let result = search(&graph, GraphSearchQuery { max_depth: Some(max_depth), ..query("A", "E") }, costs.clone(), None);
+    assert!(!result.route_found);
+    assert_eq!(result.budget_exhausted, Some(Budget::MaxDepth));
+
+    let result = search(&graph, GraphSearchQuery { max_depth: Some(max_depth + 1), ..query("A", "E") }, costs, None);
+    assert!(result.route_found);
+  }
+
+  /// A* with landmark bounds finds the same routes as plain Dijkstra on a
+  /// random graph with redirects, expanding fewer pages.
+  #[test]
+  fn landmarks_do_not_change_routes() {
+    use rand::{Rng, SeedableRng, rngs::StdRng};
+
+    let mut rng = StdRng::seed_from_u64(41);
+    let count = 300;
+    let titles: Vec<String> = (0..count).map(|index| format!("P{}", index)).collect();
+    let pages: Vec<(&str, bool)> = titles.iter().map(|title| (title.as_str(), rng.random_ratio(1, 5))).collect();
+    let regular: Vec<usize> = (0..count).filter(|&index| !pages[index].1).collect();
+    let links: Vec<Vec<usize>> = pages
+      .iter()
+      .map(|&(_, is_redirect)| match is_redirect {
+        true => vec![regular[rng.random_range(0..regular.len())]],
+        false => (0..rng.random_range(0..5)).map(|_| rng.random_range(0..count)).collect(),
+      })
+      .collect();
+    let graph = TestGraph::new(&pages, &links);
+    let oracle = Arc::new(DistanceOracle::build(graph.graph.as_ref(), 4, LandmarkSelection::Farthest));
+
+    let (mut dijkstra_visited, mut a_star_visited) = (0, 0);
+    for _ in 0..200 {
+      let query = query(&titles[rng.random_range(0..count)], &titles[rng.random_range(0..count)]);
+      let dijkstra = search(&graph, query.clone(), EdgeCostQuery::default(), None);
+      let a_star = search(&graph, query, EdgeCostQuery::default(), Some(oracle.clone()));
+
+      assert_eq!(dijkstra.route_found, a_star.route_found);
+      let distance = |result: &GraphSearchResult| result.end_node.as_ref().map(|node| node.distance);
+      assert_eq!(distance(&dijkstra), distance(&a_star));
+      let edges = |result: &GraphSearchResult| {
+        let mut edges = result.edges.clone();
+        edges.sort_unstable();
+        edges
+      };
+      assert_eq!(edges(&dijkstra), edges(&a_star));
+      dijkstra_visited += dijkstra.visited_nodes;
+      a_star_visited += a_star.visited_nodes;
+    }
+    assert!(a_star_visited < dijkstra_visited, "{} >= {}", a_star_visited, dijkstra_visited);
+  }
+}
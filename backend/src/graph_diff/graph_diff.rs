@@ -0,0 +1,339 @@
+use std::collections::HashMap;
+use serde::{Serialize, Deserialize};
+use crate::graph::GraphView;
+
+/// Compares two snapshots of the link graph, aligning pages by page id.
+pub struct GraphDiff {
+  sample_limit: usize,
+  top_limit: usize,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct GraphDiffReport {
+  pub old_node_count: usize,
+  pub new_node_count: usize,
+  pub old_edge_count: usize,
+  pub new_edge_count: usize,
+  pub added_page_count: usize,
+  pub removed_page_count: usize,
+  pub added_edge_count: usize,
+  pub removed_edge_count: usize,
+  pub changed_redirect_count: usize,
+  pub added_pages: Vec<GraphDiffPage>,
+  pub removed_pages: Vec<GraphDiffPage>,
+  pub added_edges: Vec<GraphDiffEdge>,
+  pub removed_edges: Vec<GraphDiffEdge>,
+  pub changed_redirects: Vec<GraphDiffRedirect>,
+  pub in_degree_changes: Vec<GraphDiffDegree>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct GraphDiffPage {
+  pub id: u32,
+  pub title: String,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct GraphDiffEdge {
+  pub source: GraphDiffPage,
+  pub target: GraphDiffPage,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct GraphDiffRedirect {
+  pub page: GraphDiffPage,
+  pub old_target: Option<GraphDiffPage>,
+  pub new_target: Option<GraphDiffPage>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct GraphDiffDegree {
+  pub page: GraphDiffPage,
+  pub old_in_degree: usize,
+  pub new_in_degree: usize,
+  pub delta: i64,
+}
+
+impl GraphDiffPage {
+  pub fn new<G: GraphView>(graph: &G, index: usize) -> Self {
+    GraphDiffPage {
+      id: graph.id(index),
+      title: graph.title(index).to_string(),
+    }
+  }
+}
+
+impl Default for GraphDiff {
+  fn default() -> Self {
+    Self::new(100, 20)
+  }
+}
+
+impl GraphDiff {
+  /// `sample_limit` caps each list of example pages, edges and redirects;
+  /// `top_limit` is the number of in-degree movers reported.
+  pub fn new(sample_limit: usize, top_limit: usize) -> Self {
+    GraphDiff { sample_limit, top_limit }
+  }
+
+  pub fn diff<A: GraphView, B: GraphView>(&self, old: &A, new: &B) -> GraphDiffReport {
+    let old_id_to_index = id_to_index(old);
+    let new_id_to_index = id_to_index(new);
+
+    let mut report = GraphDiffReport {
+      old_node_count: old.node_count(),
+      new_node_count: new.node_count(),
+      old_edge_count: edge_count(old),
+      new_edge_count: edge_count(new),
+      added_page_count: 0,
+      removed_page_count: 0,
+      added_edge_count: 0,
+      removed_edge_count: 0,
+      changed_redirect_count: 0,
+      added_pages: Vec::new(),
+      removed_pages: Vec::new(),
+      added_edges: Vec::new(),
+      removed_edges: Vec::new(),
+      changed_redirects: Vec::new(),
+      in_degree_changes: Vec::new(),
+    };
+
+    for index in 0..old.node_count() {
+      if !new_id_to_index.contains_key(&old.id(index)) {
+        report.removed_page_count += 1;
+        if report.removed_pages.len() < self.sample_limit {
+          report.removed_pages.push(GraphDiffPage::new(old, index));
+        }
+      }
+    }
+
+    let mut in_degree_changes = Vec::new();
+    for new_index in 0..new.node_count() {
+      let old_index = match old_id_to_index.get(&new.id(new_index)) {
+        Some(&old_index) => old_index,
+        None => {
+          report.added_page_count += 1;
+          if report.added_pages.len() < self.sample_limit {
+            report.added_pages.push(GraphDiffPage::new(new, new_index));
+          }
+          continue;
+        },
+      };
+
+      let old_target = old.redirect_target(old_index).map(|target| old.id(target));
+      let new_target = new.redirect_target(new_index).map(|target| new.id(target));
+      if old_target != new_target {
+        report.changed_redirect_count += 1;
+        if report.changed_redirects.len() < self.sample_limit {
+          report.changed_redirects.push(GraphDiffRedirect {
+            page: GraphDiffPage::new(new, new_index),
+            old_target: old.redirect_target(old_index).map(|target| GraphDiffPage::new(old, target)),
+            new_target: new.redirect_target(new_index).map(|target| GraphDiffPage::new(new, target)),
+          });
+        }
+      }
+
+      let old_in_degree = old.backward_degree(old_index);
+      let new_in_degree = new.backward_degree(new_index);
+      if old_in_degree != new_in_degree {
+        in_degree_changes.push((new_index, old_in_degree, new_in_degree));
+      }
+    }
+
+    in_degree_changes.sort_by_key(|&(index, old_in_degree, new_in_degree)| {
+      (std::cmp::Reverse(old_in_degree.abs_diff(new_in_degree)), new.id(index))
+    });
+    report.in_degree_changes = in_degree_changes
+      .into_iter()
+      .take(self.top_limit)
+      .map(|(index, old_in_degree, new_in_degree)| GraphDiffDegree {
+        page: GraphDiffPage::new(new, index),
+        old_in_degree,
+        new_in_degree,
+        delta: new_in_degree as i64 - old_in_degree as i64,
+      })
+      .collect();
+
+    // Edges are compared per source page as sorted lists of target page ids.
+    // Edges of added or removed pages count as added or removed.
+    for new_index in 0..new.node_count() {
+      let old_index = old_id_to_index.get(&new.id(new_index)).copied();
+      let mut new_targets: Vec<u32> = new.forward_neighbors(new_index).map(|n| new.id(n)).collect();
+      let mut old_targets: Vec<u32> = match old_index {
+        Some(old_index) => old.forward_neighbors(old_index).map(|n| old.id(n)).collect(),
+        None => Vec::new(),
+      };
+      new_targets.sort_unstable();
+      old_targets.sort_unstable();
+
+      let (mut i, mut j) = (0, 0);
+      while i < old_targets.len() || j < new_targets.len() {
+        if j == new_targets.len() || (i < old_targets.len() && old_targets[i] < new_targets[j]) {
+          report.removed_edge_count += 1;
+          if report.removed_edges.len() < self.sample_limit {
+            let source = old_index.unwrap();
+            let target = old_id_to_index[&old_targets[i]];
+            report.removed_edges.push(GraphDiffEdge {
+              source: GraphDiffPage::new(old, source),
+              target: GraphDiffPage::new(old, target),
+            });
+          }
+          i += 1;
+        } else if i == old_targets.len() || new_targets[j] < old_targets[i] {
+          report.added_edge_count += 1;
+          if report.added_edges.len() < self.sample_limit {
+            let target = new_id_to_index[&new_targets[j]];
+            report.added_edges.push(GraphDiffEdge {
+              source: GraphDiffPage::new(new, new_index),
+              target: GraphDiffPage::new(new, target),
+            });
+          }
+          j += 1;
+        } else {
+          i += 1;
+          j += 1;
+        }
+      }
+    }
+
+    for old_index in 0..old.node_count() {
+      if new_id_to_index.contains_key(&old.id(old_index)) {
+        continue;
+      }
+      for target in old.forward_neighbors(old_index) {
+        report.removed_edge_count += 1;
+        if report.removed_edges.len() < self.sample_limit {
+          report.removed_edges.push(GraphDiffEdge {
+            source: GraphDiffPage::new(old, old_index),
+            target: GraphDiffPage::new(old, target),
+          });
+        }
+      }
+    }
+
+    report
+  }
+}
+
+impl std::fmt::Display for GraphDiffReport {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    writeln!(f, "Nodes: {} -> {}", self.old_node_count, self.new_node_count)?;
+    writeln!(f, "Edges: {} -> {}", self.old_edge_count, self.new_edge_count)?;
+    writeln!(f, "Pages: +{} -{}", self.added_page_count, self.removed_page_count)?;
+    writeln!(f, "Links: +{} -{}", self.added_edge_count, self.removed_edge_count)?;
+    writeln!(f, "Redirects with a new target: {}", self.changed_redirect_count)?;
+
+    if !self.added_pages.is_empty() {
+      writeln!(f, "\nAdded pages:")?;
+      for page in &self.added_pages {
+        writeln!(f, "  + {} ({})", page.title, page.id)?;
+      }
+    }
+    if !self.removed_pages.is_empty() {
+      writeln!(f, "\nRemoved pages:")?;
+      for page in &self.removed_pages {
+        writeln!(f, "  - {} ({})", page.title, page.id)?;
+      }
+    }
+    if !self.changed_redirects.is_empty() {
+      writeln!(f, "\nChanged redirects:")?;
+      for redirect in &self.changed_redirects {
+        let title = |page: &Option<GraphDiffPage>| page.as_ref().map(|p| p.title.as_str()).unwrap_or("-").to_string();
+        writeln!(f, "  {}: {} -> {}", redirect.page.title, title(&redirect.old_target), title(&redirect.new_target))?;
+      }
+    }
+    if !self.in_degree_changes.is_empty() {
+      writeln!(f, "\nLargest in-degree changes:")?;
+      for change in &self.in_degree_changes {
+        writeln!(
+          f,
+          "  {:+} {} ({} -> {})",
+          change.delta, change.page.title, change.old_in_degree, change.new_in_degree,
+        )?;
+      }
+    }
+    Ok(())
+  }
+}
+
+fn id_to_index<G: GraphView>(graph: &G) -> HashMap<u32, usize> {
+  (0..graph.node_count()).map(|i| (graph.id(i), i)).collect()
+}
+
+fn edge_count<G: GraphView>(graph: &G) -> usize {
+  (0..graph.node_count()).map(|i| graph.forward_degree(i)).sum()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn graph(pages: &[(u32, &str, bool)], links: &[Vec<usize>]) -> crate::graph::Graph {
+    let nodes = pages
+      .iter()
+      .map(|&(id, title, is_redirect)| crate::graph::Node {
+        id,
+        ns: 0,
+        title: title.to_string(),
+        is_redirect,
+        is_date_related: false,
+        is_list_article: false,
+        forward_edge_range: (0, 0),
+        backward_edge_range: (0, 0),
+      })
+      .collect();
+    crate::graph::Graph::from_links(nodes, links)
+  }
+
+  fn edges(edges: &[GraphDiffEdge]) -> Vec<(&str, &str)> {
+    let mut edges: Vec<(&str, &str)> = edges.iter().map(|edge| (edge.source.title.as_str(), edge.target.title.as_str())).collect();
+    edges.sort_unstable();
+    edges
+  }
+
+  /// C is removed, D is added and R now redirects to D instead of B.
+  fn snapshots() -> (crate::graph::Graph, crate::graph::Graph) {
+    let old = graph(
+      &[(1, "A", false), (2, "B", false), (3, "C", false), (4, "R", true)],
+      &[vec![1, 2], vec![2], vec![], vec![1]],
+    );
+    let new = graph(
+      &[(4, "R", true), (1, "A", false), (2, "B", false), (5, "D", false)],
+      &[vec![3], vec![2, 3], vec![], vec![]],
+    );
+    (old, new)
+  }
+
+  #[test]
+  fn pages_links_and_redirects_are_aligned_by_id() {
+    let (old, new) = snapshots();
+    let report = GraphDiff::default().diff(&old, &new);
+
+    assert_eq!((report.old_edge_count, report.new_edge_count), (4, 3));
+    assert_eq!(report.removed_pages.iter().map(|page| page.id).collect::<Vec<_>>(), vec![3]);
+    assert_eq!(report.added_pages.iter().map(|page| page.id).collect::<Vec<_>>(), vec![5]);
+    assert_eq!(edges(&report.removed_edges), vec![("A", "C"), ("B", "C"), ("R", "B")]);
+    assert_eq!(edges(&report.added_edges), vec![("A", "D"), ("R", "D")]);
+
+    assert_eq!(report.changed_redirect_count, 1);
+    let redirect = &report.changed_redirects[0];
+    assert_eq!(redirect.page.title, "R");
+    assert_eq!(redirect.old_target.as_ref().map(|page| page.id), Some(2));
+    assert_eq!(redirect.new_target.as_ref().map(|page| page.id), Some(5));
+
+    let degrees: Vec<(&str, i64)> = report.in_degree_changes.iter().map(|change| (change.page.title.as_str(), change.delta)).collect();
+    assert_eq!(degrees, vec![("B", -1)]);
+  }
+
+  #[test]
+  fn samples_are_capped_but_counts_are_not() {
+    let (old, new) = snapshots();
+    let report = GraphDiff::new(1, 0).diff(&old, &new);
+    assert_eq!((report.removed_edge_count, report.added_edge_count), (3, 2));
+    assert_eq!((report.removed_edges.len(), report.added_edges.len()), (1, 1));
+    assert!(report.in_degree_changes.is_empty());
+
+    let report = GraphDiff::default().diff(&old, &old);
+    assert_eq!((report.added_edge_count, report.removed_edge_count, report.changed_redirect_count), (0, 0, 0));
+  }
+}
@@ -0,0 +1,7 @@
+#[allow(clippy::module_inception)]
+mod graph_diff;
+mod path_diff;
+
+pub use graph_diff::GraphDiff;
+pub use graph_diff::GraphDiffReport;
+pub use path_diff::PathDiff;
\ No newline at end of file
@@ -0,0 +1,53 @@
+use std::collections::HashSet;
+use serde::{Serialize, Deserialize};
+use crate::graph::GraphSearchResult;
+
+/// How the shortest-path DAG between two pages changed between snapshots.
+/// Nodes and edges are compared by page id.
+#[derive(Serialize, Deserialize)]
+pub struct PathDiff {
+  pub previous_distance: Option<u32>,
+  pub current_distance: Option<u32>,
+  pub added_nodes: Vec<u32>,
+  pub removed_nodes: Vec<u32>,
+  pub added_edges: Vec<(u32, u32)>,
+  pub removed_edges: Vec<(u32, u32)>,
+  pub previous: GraphSearchResult,
+  pub current: GraphSearchResult,
+}
+
+impl PathDiff {
+  pub fn new(previous: GraphSearchResult, current: GraphSearchResult) -> Self {
+    let previous_nodes: HashSet<u32> = previous.nodes.iter().map(|n| n.id).collect();
+    let current_nodes: HashSet<u32> = current.nodes.iter().map(|n| n.id).collect();
+    let previous_edges: HashSet<(u32, u32)> = previous.edges.iter().copied().collect();
+    let current_edges: HashSet<(u32, u32)> = current.edges.iter().copied().collect();
+
+    let mut added_nodes: Vec<u32> = current_nodes.difference(&previous_nodes).copied().collect();
+    let mut removed_nodes: Vec<u32> = previous_nodes.difference(&current_nodes).copied().collect();
+    let mut added_edges: Vec<(u32, u32)> = current_edges.difference(&previous_edges).copied().collect();
+    let mut removed_edges: Vec<(u32, u32)> = previous_edges.difference(&current_edges).copied().collect();
+    added_nodes.sort_unstable();
+    removed_nodes.sort_unstable();
+    added_edges.sort_unstable();
+    removed_edges.sort_unstable();
+
+    PathDiff {
+      previous_distance: distance(&previous),
+      current_distance: distance(&current),
+      added_nodes,
+      removed_nodes,
+      added_edges,
+      removed_edges,
+      previous,
+      current,
+    }
+  }
+}
+
+fn distance(result: &GraphSearchResult) -> Option<u32> {
+  if !result.route_found {
+    return None;
+  }
+  Some(result.end_node.as_ref().map(|node| node.distance).unwrap_or(0))
+}
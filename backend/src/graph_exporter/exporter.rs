@@ -0,0 +1,214 @@
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::sync::Arc;
+use quick_xml::escape::escape;
+use crate::graph::GraphView;
+use crate::graph_exporter::Format;
+
+/// Writes a graph to interchange formats for external tools.
+///
+/// Nodes are identified by their page id in every format, so exports of
+/// different subgraphs of the same dump can be joined. Redirect targets are
+/// written as the page id of the target.
+pub struct Exporter<G: GraphView = crate::graph::Graph> {
+  graph: Arc<G>,
+}
+
+impl<G: GraphView> Exporter<G> {
+  pub fn new(graph: Arc<G>) -> Self {
+    Exporter { graph }
+  }
+
+  pub fn export(&self, format: Format, path: &str) -> std::io::Result<()> {
+    match format {
+      Format::Tsv => self.write_tsv(&mut create(path)?),
+      Format::NodesTsv => self.write_nodes_tsv(&mut create(path)?),
+      Format::GraphMl => self.write_graphml(&mut create(path)?),
+      Format::Gexf => self.write_gexf(&mut create(path)?),
+      Format::Dot => self.write_dot(&mut create(path)?),
+      Format::Csv => {
+        self.write_csv_nodes(&mut create(&format!("{}.nodes.csv", path))?)?;
+        self.write_csv_edges(&mut create(&format!("{}.edges.csv", path))?)
+      }
+    }
+  }
+
+  pub fn write_tsv(&self, w: &mut impl Write) -> std::io::Result<()> {
+    writeln!(w, "# source\ttarget")?;
+    self.for_each_edge(|source, target| writeln!(w, "{}\t{}", source, target))?;
+    w.flush()
+  }
+
+  pub fn write_nodes_tsv(&self, w: &mut impl Write) -> std::io::Result<()> {
+    writeln!(w, "# id\ttitle\tflags\tns")?;
+    for index in 0..self.graph.node_count() {
+      let mut flags = Vec::new();
+      if self.graph.is_redirect(index) {
+        flags.push("redirect");
+      }
+      if self.graph.is_date_related(index) {
+        flags.push("date_related");
+      }
+      if self.graph.is_list_article(index) {
+        flags.push("list_article");
+      }
+      let flags = if flags.is_empty() { "-".to_string() } else { flags.join(",") };
+      writeln!(w, "{}\t{}\t{}\t{}", self.graph.id(index), escape_tsv(self.graph.title(index)), flags, self.graph.ns(index))?;
+    }
+    w.flush()
+  }
+
+  pub fn write_graphml(&self, w: &mut impl Write) -> std::io::Result<()> {
+    writeln!(w, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+    writeln!(w, r#"<graphml xmlns="http://graphml.graphdrawing.org/xmlns">"#)?;
+    writeln!(w, r#"  <key id="title" for="node" attr.name="title" attr.type="string"/>"#)?;
+    writeln!(w, r#"  <key id="ns" for="node" attr.name="ns" attr.type="int"/>"#)?;
+    writeln!(w, r#"  <key id="is_redirect" for="node" attr.name="is_redirect" attr.type="boolean"/>"#)?;
+    writeln!(w, r#"  <key id="is_date_related" for="node" attr.name="is_date_related" attr.type="boolean"/>"#)?;
+    writeln!(w, r#"  <key id="is_list_article" for="node" attr.name="is_list_article" attr.type="boolean"/>"#)?;
+    writeln!(w, r#"  <key id="redirect" for="node" attr.name="redirect" attr.type="long"/>"#)?;
+    writeln!(w, r#"  <graph id="G" edgedefault="directed">"#)?;
+    for index in 0..self.graph.node_count() {
+      writeln!(w, r#"    <node id="{}">"#, self.graph.id(index))?;
+      writeln!(w, r#"      <data key="title">{}</data>"#, escape(self.graph.title(index)))?;
+      writeln!(w, r#"      <data key="ns">{}</data>"#, self.graph.ns(index))?;
+      writeln!(w, r#"      <data key="is_redirect">{}</data>"#, self.graph.is_redirect(index))?;
+      writeln!(w, r#"      <data key="is_date_related">{}</data>"#, self.graph.is_date_related(index))?;
+      writeln!(w, r#"      <data key="is_list_article">{}</data>"#, self.graph.is_list_article(index))?;
+      if let Some(redirect) = self.redirect_id(index) {
+        writeln!(w, r#"      <data key="redirect">{}</data>"#, redirect)?;
+      }
+      writeln!(w, r#"    </node>"#)?;
+    }
+    self.for_each_edge(|source, target| {
+      writeln!(w, r#"    <edge source="{}" target="{}"/>"#, source, target)
+    })?;
+    writeln!(w, r#"  </graph>"#)?;
+    writeln!(w, r#"</graphml>"#)?;
+    w.flush()
+  }
+
+  pub fn write_gexf(&self, w: &mut impl Write) -> std::io::Result<()> {
+    writeln!(w, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+    writeln!(w, r#"<gexf xmlns="http://gexf.net/1.3" version="1.3">"#)?;
+    writeln!(w, r#"  <graph mode="static" defaultedgetype="directed">"#)?;
+    writeln!(w, r#"    <attributes class="node">"#)?;
+    writeln!(w, r#"      <attribute id="ns" title="ns" type="integer"/>"#)?;
+    writeln!(w, r#"      <attribute id="is_redirect" title="is_redirect" type="boolean"/>"#)?;
+    writeln!(w, r#"      <attribute id="is_date_related" title="is_date_related" type="boolean"/>"#)?;
+    writeln!(w, r#"      <attribute id="is_list_article" title="is_list_article" type="boolean"/>"#)?;
+    writeln!(w, r#"      <attribute id="redirect" title="redirect" type="long"/>"#)?;
+    writeln!(w, r#"    </attributes>"#)?;
+    writeln!(w, r#"    <nodes>"#)?;
+    for index in 0..self.graph.node_count() {
+      writeln!(w, r#"      <node id="{}" label="{}">"#, self.graph.id(index), escape(self.graph.title(index)))?;
+      writeln!(w, r#"        <attvalues>"#)?;
+      writeln!(w, r#"          <attvalue for="ns" value="{}"/>"#, self.graph.ns(index))?;
+      writeln!(w, r#"          <attvalue for="is_redirect" value="{}"/>"#, self.graph.is_redirect(index))?;
+      writeln!(w, r#"          <attvalue for="is_date_related" value="{}"/>"#, self.graph.is_date_related(index))?;
+      writeln!(w, r#"          <attvalue for="is_list_article" value="{}"/>"#, self.graph.is_list_article(index))?;
+      if let Some(redirect) = self.redirect_id(index) {
+        writeln!(w, r#"          <attvalue for="redirect" value="{}"/>"#, redirect)?;
+      }
+      writeln!(w, r#"        </attvalues>"#)?;
+      writeln!(w, r#"      </node>"#)?;
+    }
+    writeln!(w, r#"    </nodes>"#)?;
+    writeln!(w, r#"    <edges>"#)?;
+    let mut edge_id = 0u64;
+    self.for_each_edge(|source, target| {
+      edge_id += 1;
+      writeln!(w, r#"      <edge id="{}" source="{}" target="{}"/>"#, edge_id - 1, source, target)
+    })?;
+    writeln!(w, r#"    </edges>"#)?;
+    writeln!(w, r#"  </graph>"#)?;
+    writeln!(w, r#"</gexf>"#)?;
+    w.flush()
+  }
+
+  pub fn write_dot(&self, w: &mut impl Write) -> std::io::Result<()> {
+    writeln!(w, "digraph graphipedia {{")?;
+    for index in 0..self.graph.node_count() {
+      write!(
+        w,
+        "  {} [label=\"{}\", ns={}, is_redirect={}, is_date_related={}, is_list_article={}",
+        self.graph.id(index),
+        escape_dot(self.graph.title(index)),
+        self.graph.ns(index),
+        self.graph.is_redirect(index),
+        self.graph.is_date_related(index),
+        self.graph.is_list_article(index),
+      )?;
+      if let Some(redirect) = self.redirect_id(index) {
+        write!(w, ", redirect={}", redirect)?;
+      }
+      writeln!(w, "];")?;
+    }
+    self.for_each_edge(|source, target| writeln!(w, "  {} -> {};", source, target))?;
+    writeln!(w, "}}")?;
+    w.flush()
+  }
+
+  pub fn write_csv_nodes(&self, w: &mut impl Write) -> std::io::Result<()> {
+    writeln!(w, "id,title,ns,is_redirect,is_date_related,is_list_article,redirect")?;
+    for index in 0..self.graph.node_count() {
+      writeln!(
+        w,
+        "{},{},{},{},{},{},{}",
+        self.graph.id(index),
+        escape_csv(self.graph.title(index)),
+        self.graph.ns(index),
+        self.graph.is_redirect(index),
+        self.graph.is_date_related(index),
+        self.graph.is_list_article(index),
+        self.redirect_id(index).map(|id| id.to_string()).unwrap_or_default(),
+      )?;
+    }
+    w.flush()
+  }
+
+  pub fn write_csv_edges(&self, w: &mut impl Write) -> std::io::Result<()> {
+    writeln!(w, "source,target")?;
+    self.for_each_edge(|source, target| writeln!(w, "{},{}", source, target))?;
+    w.flush()
+  }
+
+  fn redirect_id(&self, index: usize) -> Option<u32> {
+    self.graph.redirect_target(index).map(|target| self.graph.id(target))
+  }
+
+  fn for_each_edge(
+    &self,
+    mut f: impl FnMut(u32, u32) -> std::io::Result<()>,
+  ) -> std::io::Result<()> {
+    for index in 0..self.graph.node_count() {
+      let source = self.graph.id(index);
+      for neighbor_index in self.graph.forward_neighbors(index) {
+        f(source, self.graph.id(neighbor_index))?;
+      }
+    }
+    Ok(())
+  }
+}
+
+fn create(path: &str) -> std::io::Result<BufWriter<File>> {
+  Ok(BufWriter::new(File::create(path)?))
+}
+
+fn escape_dot(s: &str) -> String {
+  s.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// Backslash escapes for the characters that would break a TSV row, as
+/// read back by `Importer::read_nodes`.
+fn escape_tsv(s: &str) -> String {
+  s.replace('\\', "\\\\").replace('\t', "\\t").replace('\n', "\\n").replace('\r', "\\r")
+}
+
+fn escape_csv(s: &str) -> String {
+  if s.contains([',', '"', '\n', '\r']) {
+    format!("\"{}\"", s.replace('"', "\"\""))
+  } else {
+    s.to_string()
+  }
+}
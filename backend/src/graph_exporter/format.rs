@@ -0,0 +1,28 @@
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+  /// Tab separated `source target` page id pairs.
+  Tsv,
+  /// Tab separated `id title flags ns` rows, readable by the importer.
+  NodesTsv,
+  GraphMl,
+  Gexf,
+  Dot,
+  /// A `<prefix>.nodes.csv` / `<prefix>.edges.csv` pair.
+  Csv,
+}
+
+impl std::str::FromStr for Format {
+  type Err = String;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s.to_lowercase().as_str() {
+      "tsv" => Ok(Format::Tsv),
+      "nodes-tsv" => Ok(Format::NodesTsv),
+      "graphml" => Ok(Format::GraphMl),
+      "gexf" => Ok(Format::Gexf),
+      "dot" => Ok(Format::Dot),
+      "csv" => Ok(Format::Csv),
+      _ => Err(format!("unknown export format: {}", s)),
+    }
+  }
+}
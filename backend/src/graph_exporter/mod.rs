@@ -0,0 +1,5 @@
+mod format;
+mod exporter;
+
+pub use format::Format;
+pub use exporter::Exporter;
\ No newline at end of file
@@ -0,0 +1,219 @@
+use std::collections::{HashMap, HashSet};
+use std::io::{BufRead, Error, ErrorKind};
+
+/// Builds a `Graph` from hand written or externally generated TSV files, so
+/// corpora other than Wikipedia dumps can be served.
+///
+/// The nodes file has one `id<TAB>title<TAB>flags[<TAB>ns]` row per page,
+/// where `flags` is a comma separated subset of `redirect`, `date_related`
+/// and `list_article` (empty or `-` for none) and `ns` defaults to 0. Tabs,
+/// line breaks and backslashes in titles are escaped as `\t`, `\n`, `\r`
+/// and `\\`. The edges file has one `source<TAB>target` row of page ids per
+/// link. Blank lines and lines starting with `#` are ignored in both files.
+/// A redirect points to the target of its first edge.
+pub struct Importer {
+  nodes: Vec<crate::graph::Node>,
+  id_to_index: HashMap<u32, usize>,
+  titles: HashSet<String>,
+  links: Vec<Vec<usize>>,
+  link_set: HashSet<(usize, usize)>,
+}
+
+impl Default for Importer {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+impl Importer {
+  pub fn new() -> Self {
+    Importer {
+      nodes: Vec::new(),
+      id_to_index: HashMap::new(),
+      titles: HashSet::new(),
+      links: Vec::new(),
+      link_set: HashSet::new(),
+    }
+  }
+
+  pub fn read_nodes(&mut self, reader: impl BufRead) -> std::io::Result<()> {
+    for (line_number, line) in reader.lines().enumerate() {
+      let line = line?;
+      if line.is_empty() || line.starts_with('#') {
+        continue;
+      }
+      let invalid = |message: &str| invalid_data(line_number, message, &line);
+
+      let columns: Vec<&str> = line.split('\t').collect();
+      if columns.len() < 3 || columns.len() > 4 {
+        return Err(invalid("expected id, title, flags and optional ns"));
+      }
+      let id: u32 = columns[0].parse().map_err(|_| invalid("invalid id"))?;
+      let title = unescape_tsv(columns[1]);
+      let ns: i32 = match columns.get(3) {
+        Some(ns) => ns.parse().map_err(|_| invalid("invalid ns"))?,
+        None => 0,
+      };
+
+      let mut node = crate::graph::Node {
+        id,
+        ns,
+        title,
+        is_redirect: false,
+        is_date_related: false,
+        is_list_article: false,
+        forward_edge_range: (0, 0),
+        backward_edge_range: (0, 0),
+      };
+      for flag in columns[2].split(',').map(str::trim) {
+        match flag {
+          "" | "-" => {},
+          "redirect" => node.is_redirect = true,
+          "date_related" => node.is_date_related = true,
+          "list_article" => node.is_list_article = true,
+          _ => return Err(invalid("unknown flag")),
+        }
+      }
+
+      if self.id_to_index.contains_key(&id) {
+        return Err(invalid("duplicate id"));
+      }
+      if !self.titles.insert(node.title.clone()) {
+        return Err(invalid("duplicate title"));
+      }
+      self.id_to_index.insert(id, self.nodes.len());
+      self.nodes.push(node);
+      self.links.push(Vec::new());
+    }
+    Ok(())
+  }
+
+  /// Reads links between pages added by `read_nodes`. Duplicate links are
+  /// dropped, keeping the first occurrence.
+  pub fn read_edges(&mut self, reader: impl BufRead) -> std::io::Result<()> {
+    for (line_number, line) in reader.lines().enumerate() {
+      let line = line?;
+      if line.is_empty() || line.starts_with('#') {
+        continue;
+      }
+      let invalid = |message: &str| invalid_data(line_number, message, &line);
+
+      let (source, target) = line.split_once('\t').ok_or_else(|| invalid("expected source and target"))?;
+      let source: u32 = source.parse().map_err(|_| invalid("invalid source id"))?;
+      let target: u32 = target.trim_end().parse().map_err(|_| invalid("invalid target id"))?;
+      let &source_index = self.id_to_index.get(&source).ok_or_else(|| invalid("unknown source id"))?;
+      let &target_index = self.id_to_index.get(&target).ok_or_else(|| invalid("unknown target id"))?;
+
+      if self.link_set.insert((source_index, target_index)) {
+        self.links[source_index].push(target_index);
+      }
+    }
+    Ok(())
+  }
+
+  pub fn build(self) -> std::io::Result<crate::graph::Graph> {
+    for (index, node) in self.nodes.iter().enumerate() {
+      if node.is_redirect && self.links[index].is_empty() {
+        return Err(Error::new(
+          ErrorKind::InvalidData,
+          format!("redirect {} ({}) has no target", node.id, node.title),
+        ));
+      }
+    }
+    Ok(crate::graph::Graph::from_links(self.nodes, &self.links))
+  }
+}
+
+/// Undoes the escapes of `Exporter::write_nodes_tsv`, keeping any other
+/// backslash as it is.
+fn unescape_tsv(s: &str) -> String {
+  let mut result = String::with_capacity(s.len());
+  let mut chars = s.chars();
+  while let Some(c) = chars.next() {
+    if c != '\\' {
+      result.push(c);
+      continue;
+    }
+    match chars.next() {
+      Some('t') => result.push('\t'),
+      Some('n') => result.push('\n'),
+      Some('r') => result.push('\r'),
+      Some('\\') => result.push('\\'),
+      Some(other) => {
+        result.push('\\');
+        result.push(other);
+      },
+      None => result.push('\\'),
+    }
+  }
+  result
+}
+
+fn invalid_data(line_number: usize, message: &str, line: &str) -> Error {
+  Error::new(ErrorKind::InvalidData, format!("line {}: {}: {:?}", line_number + 1, message, line))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::sync::Arc;
+  use crate::graph::GraphView;
+  use crate::graph_exporter::Exporter;
+
+  fn import_error(nodes: &str, edges: &str) -> String {
+    import(nodes, edges).err().unwrap().to_string()
+  }
+
+  fn import(nodes: &str, edges: &str) -> std::io::Result<crate::graph::Graph> {
+    let mut importer = Importer::new();
+    importer.read_nodes(nodes.as_bytes())?;
+    importer.read_edges(edges.as_bytes())?;
+    importer.build()
+  }
+
+  #[test]
+  fn exported_tsv_imports_as_the_same_graph() {
+    let nodes = "# id\ttitle\tflags\tns\n\
+      7\tTab\\there\t-\t0\n\
+      3\tBack\\\\slash\\nand line\tdate_related,list_article\t4\n\
+      9\tRedirect\tredirect\n\
+      \n\
+      1\tPlain\t\t0\n";
+    let edges = "9\t1\n7\t3\n7\t1\n7\t3\n3\t7\n";
+    let graph = import(nodes, edges).unwrap();
+    assert_eq!(graph.title(0), "Tab\there");
+    assert_eq!(graph.title(1), "Back\\slash\nand line");
+    assert_eq!((graph.ns(1), graph.is_date_related(1), graph.is_list_article(1)), (4, true, true));
+    assert_eq!(graph.redirect_target(2), Some(3));
+    // The duplicate 7 -> 3 link is dropped.
+    assert_eq!(graph.edge_count(), 4);
+
+    let graph = Arc::new(graph);
+    let exporter = Exporter::new(graph.clone());
+    let (mut nodes, mut edges) = (Vec::new(), Vec::new());
+    exporter.write_nodes_tsv(&mut nodes).unwrap();
+    exporter.write_tsv(&mut edges).unwrap();
+    let reimported = import(std::str::from_utf8(&nodes).unwrap(), std::str::from_utf8(&edges).unwrap()).unwrap();
+
+    assert_eq!(reimported.fingerprint(), graph.fingerprint());
+    for index in 0..graph.node_count() {
+      assert_eq!(reimported.title(index), graph.title(index));
+      assert_eq!(reimported.ns(index), graph.ns(index));
+      assert_eq!(reimported.is_redirect(index), graph.is_redirect(index));
+      assert_eq!(reimported.is_date_related(index), graph.is_date_related(index));
+      assert_eq!(reimported.is_list_article(index), graph.is_list_article(index));
+    }
+  }
+
+  #[test]
+  fn bad_rows_are_reported_with_their_line() {
+    let error = import_error("1\tA\t-\n1\tB\t-\n", "");
+    assert!(error.starts_with("line 2: duplicate id"), "{}", error);
+
+    let error = import_error("1\tA\t-\n", "1\t2\n");
+    assert!(error.starts_with("line 1: unknown target id"), "{}", error);
+
+    let error = import_error("1\tA\tredirect\n", "");
+    assert!(error.contains("has no target"), "{}", error);
+  }
+}
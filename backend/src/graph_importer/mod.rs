@@ -0,0 +1,3 @@
+mod importer;
+
+pub use importer::Importer;
\ No newline at end of file
@@ -0,0 +1,4 @@
+mod updater;
+
+pub use updater::Updater;
+pub use updater::UpdateSummary;
\ No newline at end of file
@@ -0,0 +1,274 @@
+use std::collections::{HashMap, HashSet};
+use serde::{Serialize, Deserialize};
+use crate::graph::{EdgeAttribute, EdgeAttributes, GraphView};
+
+/// Applies changed, added and deleted pages to an existing `Graph`.
+///
+/// Pages from an adds-changes dump are registered first so links between
+/// them resolve, then scraped and applied. Nodes that did not change keep
+/// their adjacency from the old graph (minus links to deleted pages), so only
+/// changed pages need their text. Links from unchanged pages to titles that
+/// only now exist are not discovered until those pages change. Edge
+/// attributes of the old graph are carried over the same way, with changed
+/// pages getting the attributes of their new links.
+///
+/// Each update is a new version of the graph, written next to the old one
+/// (see `path_for`) so the graph being served is never overwritten.
+pub struct Updater {
+  graph: crate::graph::Graph,
+  /// Attributes of the old graph's links, when they match it.
+  edge_attributes: Option<EdgeAttributes>,
+  id_to_index: HashMap<u32, usize>,
+  registered: HashMap<u32, (String, i32)>,
+  scraped: HashMap<u32, crate::wikipedia_page_scraper::Page>,
+  deleted: HashSet<u32>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct UpdateSummary {
+  pub added_pages: usize,
+  pub changed_pages: usize,
+  pub deleted_pages: usize,
+  pub unchanged_pages: usize,
+  pub old_edge_count: usize,
+  pub new_edge_count: usize,
+  /// `GraphView::fingerprint` of the graph the update was applied to.
+  pub base_fingerprint: u64,
+  /// `GraphView::fingerprint` of the updated graph.
+  pub fingerprint: u64,
+}
+
+impl UpdateSummary {
+  /// `graph.v2.bin` becomes `graph.v2.update.json`.
+  pub fn path_for(graph_path: &str) -> String {
+    match graph_path.strip_suffix(".bin") {
+      Some(stem) => format!("{}.update.json", stem),
+      None => format!("{}.update.json", graph_path),
+    }
+  }
+}
+
+impl Updater {
+  pub fn new(graph: crate::graph::Graph) -> Self {
+    let id_to_index = graph.nodes
+      .iter()
+      .enumerate()
+      .map(|(i, node)| (node.id, i))
+      .collect();
+
+    Updater {
+      graph,
+      edge_attributes: None,
+      id_to_index,
+      registered: HashMap::new(),
+      scraped: HashMap::new(),
+      deleted: HashSet::new(),
+    }
+  }
+
+  /// Carries `edge_attributes` over to the updated graph. Attributes
+  /// written for another graph are left out.
+  pub fn with_edge_attributes(mut self, edge_attributes: EdgeAttributes) -> Self {
+    self.edge_attributes = Some(edge_attributes).filter(|attributes| attributes.matches(&self.graph));
+    self
+  }
+
+  /// Where the next version of the graph at `graph_path` goes: `graph.bin`
+  /// becomes `graph.v1.bin`, and `graph.v1.bin` becomes `graph.v2.bin`.
+  pub fn path_for(graph_path: &str) -> String {
+    let stem = graph_path.strip_suffix(".bin").unwrap_or(graph_path);
+    let (stem, version) = match stem.rsplit_once(".v") {
+      Some((base, version)) => match version.parse::<u32>() {
+        Ok(version) => (base, version),
+        Err(_) => (stem, 0),
+      },
+      None => (stem, 0),
+    };
+    format!("{}.v{}.bin", stem, version + 1)
+  }
+
+  /// Announces a page that will be applied, so links to it can be resolved
+  /// while scraping the other changed pages.
+  pub fn register(&mut self, page: &crate::wikipedia_xml_parser::Page) {
+    self.registered.insert(page.id, (page.title.clone(), page.ns));
+  }
+
+  /// Marks a page as deleted. Pages that are also applied are kept.
+  pub fn delete(&mut self, id: u32) {
+    self.deleted.insert(id);
+  }
+
+  /// Title to page id map of the updated graph, for the `Scraper`.
+  pub fn title_to_id_map(&self) -> HashMap<String, u32> {
+    let mut title_to_id_map: HashMap<String, u32> = self.graph.nodes
+      .iter()
+      .filter(|node| node.ns == 0)
+      .filter(|node| !self.registered.contains_key(&node.id) && !self.deleted.contains(&node.id))
+      .map(|node| (node.title.clone(), node.id))
+      .collect();
+
+    for (&id, (title, ns)) in &self.registered {
+      if *ns == 0 {
+        title_to_id_map.insert(title.clone(), id);
+      }
+    }
+    title_to_id_map
+  }
+
+  pub fn apply(&mut self, page: crate::wikipedia_page_scraper::Page) {
+    self.scraped.insert(page.id, page);
+  }
+
+  /// The updated graph, with edge attributes when the old graph had them.
+  pub fn build(self) -> (crate::graph::Graph, Option<EdgeAttributes>, UpdateSummary) {
+    let mut nodes = Vec::new();
+    let mut old_to_new = vec![None; self.graph.nodes.len()];
+    let mut changed_pages = 0;
+    let mut deleted_pages = 0;
+
+    for (old_index, node) in self.graph.nodes.iter().enumerate() {
+      if let Some(page) = self.scraped.get(&node.id) {
+        changed_pages += 1;
+        old_to_new[old_index] = Some(nodes.len());
+        nodes.push(crate::graph::Node::new(page));
+      } else if self.deleted.contains(&node.id) {
+        deleted_pages += 1;
+      } else {
+        old_to_new[old_index] = Some(nodes.len());
+        nodes.push(crate::graph::Node {
+          id: node.id,
+          ns: node.ns,
+          title: node.title.clone(),
+          is_redirect: node.is_redirect,
+          is_date_related: node.is_date_related,
+          is_list_article: node.is_list_article,
+          forward_edge_range: (0, 0),
+          backward_edge_range: (0, 0),
+        });
+      }
+    }
+
+    let mut added: Vec<&crate::wikipedia_page_scraper::Page> = self.scraped
+      .values()
+      .filter(|page| !self.id_to_index.contains_key(&page.id))
+      .collect();
+    added.sort_by_key(|page| page.id);
+    for page in &added {
+      nodes.push(crate::graph::Node::new(page));
+    }
+
+    let id_to_new_index: HashMap<u32, usize> = nodes
+      .iter()
+      .enumerate()
+      .map(|(i, node)| (node.id, i))
+      .collect();
+
+    // Each link with its attribute, which is only kept when the old graph
+    // had attributes.
+    let (links, attributes): (Vec<Vec<usize>>, Vec<Vec<EdgeAttribute>>) = nodes
+      .iter()
+      .map(|node| match self.scraped.get(&node.id) {
+        Some(page) => page.linked_pages
+          .iter()
+          .zip(&page.link_attributes)
+          .filter_map(|(id, &attribute)| Some((*id_to_new_index.get(id)?, attribute)))
+          .unzip(),
+        None => {
+          let old_index = self.id_to_index[&node.id];
+          let (s, e) = self.graph.nodes[old_index].forward_edge_range;
+          (s..e)
+            .filter_map(|edge| {
+              let attribute = self.edge_attributes
+                .as_ref()
+                .map(|attributes| attributes.get(&self.graph, old_index, edge - s))
+                .unwrap_or_default();
+              Some((old_to_new[self.graph.forward_edges[edge]]?, attribute))
+            })
+            .unzip()
+        },
+      })
+      .unzip();
+
+    let graph = crate::graph::Graph::from_links(nodes, &links);
+    let edge_attributes = self.edge_attributes
+      .is_some()
+      .then(|| EdgeAttributes::new(&graph, attributes.concat().into_boxed_slice()));
+
+    let summary = UpdateSummary {
+      added_pages: added.len(),
+      changed_pages,
+      deleted_pages,
+      unchanged_pages: graph.nodes.len() - added.len() - changed_pages,
+      old_edge_count: self.graph.forward_edges.len(),
+      new_edge_count: graph.forward_edges.len(),
+      base_fingerprint: self.graph.fingerprint(),
+      fingerprint: graph.fingerprint(),
+    };
+    (graph, edge_attributes, summary)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::wikipedia_page_scraper::Page;
+
+  fn page(id: u32, title: &str, links: &[(u32, u8)]) -> Page {
+    Page {
+      id,
+      title: title.to_string(),
+      ns: 0,
+      is_redirect: false,
+      linked_pages: links.iter().map(|&(id, _)| id).collect(),
+      link_attributes: links.iter().map(|&(_, position)| EdgeAttribute { position, in_template: false }).collect(),
+      is_date_related: false,
+      is_list_article: false,
+    }
+  }
+
+  /// A -> B, A -> C, B -> C, with positions 1, 2 and 3.
+  fn old_graph() -> (crate::graph::Graph, EdgeAttributes) {
+    let pages = [page(1, "A", &[(2, 1), (3, 2)]), page(2, "B", &[(3, 3)]), page(3, "C", &[])];
+    let nodes = pages.iter().map(crate::graph::Node::new).collect();
+    let graph = crate::graph::Graph::from_links(nodes, &[vec![1, 2], vec![2], vec![]]);
+    let attributes = pages.iter().flat_map(|page| page.link_attributes.clone()).collect();
+    let edge_attributes = EdgeAttributes::new(&graph, attributes);
+    (graph, edge_attributes)
+  }
+
+  #[test]
+  fn update_carries_edge_attributes_over() {
+    let (graph, edge_attributes) = old_graph();
+    let mut updater = Updater::new(graph).with_edge_attributes(edge_attributes);
+    // B is rewritten to link to the new page D, and C is deleted.
+    updater.delete(3);
+    updater.apply(page(2, "B", &[(4, 7), (3, 8)]));
+    updater.apply(page(4, "D", &[(1, 9)]));
+    let (graph, edge_attributes, summary) = updater.build();
+
+    assert_eq!((summary.added_pages, summary.changed_pages, summary.deleted_pages), (1, 1, 1));
+    let edge_attributes = edge_attributes.unwrap();
+    assert!(edge_attributes.matches(&graph));
+    let links = |title: &str| {
+      let index = graph.nodes.iter().position(|node| node.title == title).unwrap();
+      (0..graph.forward_degree(index))
+        .map(|offset| {
+          let target = graph.forward_edges[graph.forward_edge_start(index) + offset];
+          (graph.nodes[target].title.as_str(), edge_attributes.get(&graph, index, offset).position)
+        })
+        .collect::<Vec<_>>()
+    };
+    assert_eq!(links("A"), vec![("B", 1)]);
+    assert_eq!(links("B"), vec![("D", 7)]);
+    assert_eq!(links("D"), vec![("A", 9)]);
+    assert_eq!(summary.fingerprint, graph.fingerprint());
+  }
+
+  #[test]
+  fn versioned_paths() {
+    assert_eq!(Updater::path_for("graph.bin"), "graph.v1.bin");
+    assert_eq!(Updater::path_for("data/graph.v1.bin"), "data/graph.v2.bin");
+    assert_eq!(Updater::path_for("graph.vx.bin"), "graph.vx.v1.bin");
+    assert_eq!(UpdateSummary::path_for("graph.v2.bin"), "graph.v2.update.json");
+  }
+}
@@ -2,4 +2,8 @@ pub mod wikipedia_xml_parser;
 pub mod wikipedia_page_scraper;
 pub mod graph;
 pub mod api;
-pub mod title_search;
\ No newline at end of file
+pub mod title_search;
+pub mod graph_exporter;
+pub mod graph_importer;
+pub mod graph_updater;
+pub mod graph_diff;
\ No newline at end of file
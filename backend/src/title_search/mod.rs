@@ -1,3 +1,4 @@
+#[allow(clippy::module_inception)]
 mod title_search;
 pub use title_search::TitleSearch;
 pub use title_search::TitleSearchQuery;
@@ -1,9 +1,10 @@
 use std::sync::Arc;
 use serde::{Serialize, Deserialize };
+use crate::graph::GraphView;
 
-pub struct TitleSearch {
+pub struct TitleSearch<G: GraphView = crate::graph::Graph> {
   sorted_title: Vec<(String, usize)>,
-  graph: Arc<crate::graph::Graph>
+  graph: Arc<G>
 }
 
 #[derive(Serialize, Deserialize)]
@@ -31,15 +32,12 @@ pub struct TitleSearchResultItem {
   pub backward_link_count: usize,
 }
 
-impl TitleSearch {
-  pub fn new(graph: Arc<crate::graph::Graph>) -> Self {
-    let mut sorted_title: Vec<_> = graph
-    .nodes
-    .iter()
-    .enumerate()
-    .map(|(i, page)| {
-      let lower_case_title = page.title.to_lowercase();
-      return (lower_case_title, i);
+impl<G: GraphView> TitleSearch<G> {
+  pub fn new(graph: Arc<G>) -> Self {
+    let mut sorted_title: Vec<_> = (0..graph.node_count())
+    .map(|i| {
+      let lower_case_title = graph.title(i).to_lowercase();
+      (lower_case_title, i)
     })
     .collect();
 
@@ -60,34 +58,34 @@ impl TitleSearch {
       .take_while(|t| t.0.starts_with(&query))
       .take(limit)
       .for_each(|t| {
-        let page = &self.graph.nodes[t.1];
+        let index = t.1;
 
-        let (redirect_title, redirect_id) = if page.is_redirect {
-          let redirect_index = self.graph.forward_edges[page.forward_edge_range.0];
-          let redriect_page = &self.graph.nodes[redirect_index];
-          (Some(redriect_page.title.clone()), Some(redriect_page.id))
-        } else {
-          (None, None)
+        let (redirect_title, redirect_id) = match self.graph.redirect_target(index) {
+          Some(redirect_index) => (
+            Some(self.graph.title(redirect_index).to_string()),
+            Some(self.graph.id(redirect_index)),
+          ),
+          None => (None, None),
         };
 
-        let fowerd_link_count = page.forward_edge_range.1 - page.forward_edge_range.0;
-        let backward_link_count = page.backward_edge_range.1 - page.backward_edge_range.0;
+        let fowerd_link_count = self.graph.forward_degree(index);
+        let backward_link_count = self.graph.backward_degree(index);
         let link_count = fowerd_link_count + backward_link_count;
 
         result.push(TitleSearchResultItem {
-          title: page.title.clone(),
-          id: page.id,
-          is_redirect: page.is_redirect,
+          title: self.graph.title(index).to_string(),
+          id: self.graph.id(index),
+          is_redirect: self.graph.is_redirect(index),
           redirected_title: redirect_title,
           redirected_id: redirect_id,
           forward_link_count: fowerd_link_count,
-          backward_link_count: backward_link_count,
-          link_count: link_count
+          backward_link_count,
+          link_count
         });
       });
 
     TitleSearchResult {
-      query: TitleSearchQuery { query: query, limit: Some(limit) },
+      query: TitleSearchQuery { query, limit: Some(limit) },
       items: result,
       duration: start_time.elapsed(),
     }
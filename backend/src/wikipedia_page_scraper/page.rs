@@ -4,6 +4,8 @@ pub struct Page {
   pub ns: i32,
   pub is_redirect: bool,
   pub linked_pages: Vec<u32>,
+  /// Parallel to `linked_pages`.
+  pub link_attributes: Vec<crate::graph::EdgeAttribute>,
   pub is_date_related: bool,
   pub is_list_article: bool,
 }
\ No newline at end of file
@@ -19,41 +19,84 @@ impl Scraper {
   }
 
   pub fn scrape(&self, page: &crate::wikipedia_xml_parser::Page) -> crate::wikipedia_page_scraper::Page {
+    let (linked_pages, link_attributes) = self.linked_pages(&page.text);
     crate::wikipedia_page_scraper::Page {
       id: page.id,
       title: page.title.clone(),
       ns: page.ns,
       is_redirect: is_redirect(&page.text),
-      linked_pages: self.linked_pages(&page.text),
+      linked_pages,
+      link_attributes,
       is_date_related: is_date_related(&page.title),
       is_list_article: is_list_article(&page.title),
     }
   }
-  fn linked_pages(&self, text: &str) -> Vec<u32> {
-    let mut result = std::collections::HashSet::new();
-    LINK_REGEX
-      .captures_iter(text)
-      .filter_map(|cap| {
-        if let Some(m) = cap.get(1) {
-          Some(m.as_str().to_string())
-        } else {
-          None
-        }
-      })
-      .filter_map(|title| {
-        let id = self.title_to_id_map.get(&title);
-        if id.is_some() {
-          Some(id.unwrap().clone())
-        } else {
-          None
-        }
-      }).for_each(|id| {
-        result.insert(id);
-      });
-    return result.into_iter().collect();
+
+  /// Linked page ids in order of first occurrence, with their attributes.
+  fn linked_pages(&self, text: &str) -> (Vec<u32>, Vec<crate::graph::EdgeAttribute>) {
+    let templates = template_ranges(text);
+    let mut index_of: std::collections::HashMap<u32, usize> = std::collections::HashMap::new();
+    let mut linked_pages = Vec::new();
+    let mut link_attributes: Vec<crate::graph::EdgeAttribute> = Vec::new();
+    for cap in LINK_REGEX.captures_iter(text) {
+      let (Some(link), Some(title)) = (cap.get(0), cap.get(1)) else {
+        continue;
+      };
+      let Some(&id) = self.title_to_id_map.get(title.as_str()) else {
+        continue;
+      };
+      let in_template = in_ranges(&templates, link.start());
+      match index_of.get(&id) {
+        Some(&i) => link_attributes[i].in_template &= in_template,
+        None => {
+          index_of.insert(id, linked_pages.len());
+          linked_pages.push(id);
+          link_attributes.push(crate::graph::EdgeAttribute {
+            position: (link.start() * 255 / text.len().max(1)) as u8,
+            in_template,
+          });
+        },
+      }
+    }
+    (linked_pages, link_attributes)
   }
 }
 
+/// Byte ranges of the outermost `{{...}}` templates.
+fn template_ranges(text: &str) -> Vec<(usize, usize)> {
+  let bytes = text.as_bytes();
+  let mut ranges = Vec::new();
+  let mut depth = 0usize;
+  let mut start = 0;
+  let mut i = 0;
+  while i + 1 < bytes.len() {
+    if bytes[i] == b'{' && bytes[i + 1] == b'{' {
+      if depth == 0 {
+        start = i;
+      }
+      depth += 1;
+      i += 2;
+    } else if bytes[i] == b'}' && bytes[i + 1] == b'}' && depth > 0 {
+      depth -= 1;
+      if depth == 0 {
+        ranges.push((start, i + 2));
+      }
+      i += 2;
+    } else {
+      i += 1;
+    }
+  }
+  if depth > 0 {
+    ranges.push((start, bytes.len()));
+  }
+  ranges
+}
+
+fn in_ranges(ranges: &[(usize, usize)], offset: usize) -> bool {
+  let i = ranges.partition_point(|&(start, _)| start <= offset);
+  i > 0 && offset < ranges[i - 1].1
+}
+
 
 fn is_date_related(title: &str) -> bool {
     NEN_REGEX.is_match(title)
@@ -63,9 +63,9 @@ impl<'a, B: std::io::BufRead> Iterator for PageIterator<'a, B> {
               self.tags.pop();
               return Some(Ok(crate::wikipedia_xml_parser::Page {
                 id: id_str.parse().unwrap(),
-                title: title,
+                title,
                 ns: ns_str.parse().unwrap(),
-                text: text,
+                text,
               }));
             },
             _ => {
@@ -20,12 +20,57 @@ export interface SearchGraphResult {
   end_node: SearchGraphNode;
   nodes: SearchGraphNode[];
   edges: number[][];
+  edge_directions: ('forward' | 'backward')[];
   start_not_found: boolean;
   end_not_found: boolean;
   route_found: boolean;
   is_start_end_same: boolean;
+  distance_overflow: boolean;
+  truncated: boolean;
+  dropped_nodes: number;
+  dropped_paths: number;
+  avoid_not_found: string[];
+  via_not_found: string[];
+  budget_exhausted: 'max_visited' | 'max_depth' | 'timeout' | 'cancelled' | null;
+  front_frontier: number;
+  back_frontier: number;
   duration: {
     secs: number;
     nanos: number;
   }
-}
\ No newline at end of file
+}
+
+export interface SearchGraphProgress {
+  level: number;
+  front_frontier: number;
+  back_frontier: number;
+  discovered_nodes: number;
+  visited_nodes: number;
+  meeting_nodes: SearchGraphNode[];
+}
+
+export interface SearchGraphBatchItem {
+  start: string;
+  end: string;
+  start_not_found: boolean;
+  end_not_found: boolean;
+  route_found: boolean;
+  distance: number | null;
+  path_count: number;
+  path_count_saturated: boolean;
+  path: SearchGraphNode[] | null;
+  budget_exhausted: SearchGraphResult['budget_exhausted'];
+  duration: {
+    secs: number;
+    nanos: number;
+  }
+}
+
+export interface SearchGraphBatchResult {
+  items: SearchGraphBatchItem[];
+  route_found: number;
+  duration: {
+    secs: number;
+    nanos: number;
+  }
+}